@@ -0,0 +1,78 @@
+//! Compares the load times of the HUGR JSON and hugr-model binary encodings.
+//!
+//! Run with `cargo bench --bench model_load`. The size of the generated
+//! program can be set with the `FUNCTIONS` and `DEPTH` environment variables.
+
+use std::time::{Duration, Instant};
+
+use guppyc::compile::hugr::HugrStage;
+use hugr::Hugr;
+use hugr::builder::{Dataflow, DataflowSubContainer, HugrBuilder, ModuleBuilder};
+use hugr::extension::prelude::bool_t;
+use hugr::package::Package;
+use hugr::std_extensions::logic::LogicOp;
+use hugr::types::Signature;
+
+const ITERATIONS: u32 = 10;
+
+fn main() -> anyhow::Result<()> {
+    let functions = env_or("FUNCTIONS", 200);
+    let depth = env_or("DEPTH", 200);
+
+    let stage = HugrStage {
+        pkg: Package::new([synthetic_hugr(functions, depth)?])?,
+    };
+    let json = stage.pkg.to_json()?;
+    let binary = stage.to_binary();
+
+    println!(
+        "Synthetic HUGR with {} nodes ({functions} functions of depth {depth})",
+        stage.hugr().node_count()
+    );
+    println!("  json:   {:>10} bytes", json.len());
+    println!("  binary: {:>10} bytes", binary.len());
+
+    let registry = guppyc::extensions::default_registry();
+    let json_time = time(|| HugrStage::from_json(&json, &registry).map(|_| ()))?;
+    let binary_time = time(|| HugrStage::from_binary(&binary, &registry).map(|_| ()))?;
+    println!("Average load time over {ITERATIONS} iterations");
+    println!("  json:   {json_time:?}");
+    println!("  binary: {binary_time:?}");
+
+    Ok(())
+}
+
+/// Build a module with `functions` functions, each a chain of `depth` logic operations.
+fn synthetic_hugr(functions: usize, depth: usize) -> anyhow::Result<Hugr> {
+    let mut module = ModuleBuilder::new();
+    for i in 0..functions {
+        let mut func = module.define_function(
+            format!("f{i}"),
+            Signature::new_endo(vec![bool_t(), bool_t()]),
+        )?;
+        let [mut a, mut b] = func.input_wires_arr();
+        for _ in 0..depth {
+            let out = func.add_dataflow_op(LogicOp::And, [a, b])?.out_wire(0);
+            a = b;
+            b = out;
+        }
+        func.finish_with_outputs([a, b])?;
+    }
+    Ok(module.finish_hugr()?)
+}
+
+/// Average the runtime of `f` over [ITERATIONS] runs.
+fn time(f: impl Fn() -> anyhow::Result<()>) -> anyhow::Result<Duration> {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f()?;
+    }
+    Ok(start.elapsed() / ITERATIONS)
+}
+
+fn env_or(var: &str, default: usize) -> usize {
+    std::env::var(var)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
@@ -1,19 +1,29 @@
 """Methods for compiling Guppy programs into HUGRs."""
 
+import ast
+import contextlib
 import importlib.machinery
 import types
+from collections.abc import Iterator
 from pathlib import Path
 import sys
+from typing import Any
 
 try:
     import guppylang
+    from guppylang.ast_util import AstVisitor
     from guppylang.module import GuppyModule
+    from guppylang.span import to_span
     from guppylang import guppy
+    from hugr import Hugr, ops
 except ImportError as e:
     raise RuntimeError("The `guppylang` python dependency is not installed.") from e
 
 MINIMUM_GUPPY_VERSION: str = "0.14.0"
 
+# Metadata key holding the source location of a node.
+SOURCE_LOCATION_METADATA: str = "guppyc.source"
+
 
 class GuppyCompiler:
     """A processor for compiling Guppy programs into Hugrs."""
@@ -36,9 +46,38 @@ class GuppyCompiler:
             raise InvalidGuppyModulePathError(input_path) from err
 
         module: GuppyModule = self._get_module(py_module, input_path)
-        pkg = module.compile().package
+        with _record_node_locations(input_path):
+            pkg = module.compile().package
+        for hugr in pkg.modules:
+            self._add_source_locations(hugr, input_path)
         return pkg.to_json()
 
+    def _add_source_locations(self, hugr: Hugr, source_path: Path) -> None:
+        """Attach the source location of each function definition to its node.
+
+        The nodes in the function bodies are located while compiling, see
+        `_record_node_locations`. Function definitions may be compiled while
+        visiting a call, so they are matched by name instead, skipping names
+        defined more than once.
+        """
+        tree = ast.parse(source_path.read_text(), filename=str(source_path))
+        definitions: dict[str, list[ast.FunctionDef]] = {}
+        for definition in ast.walk(tree):
+            if isinstance(definition, ast.FunctionDef):
+                definitions.setdefault(definition.name, []).append(definition)
+
+        for _, data in hugr.nodes():
+            if not isinstance(data.op, ops.FuncDefn):
+                continue
+            candidates = definitions.get(data.op.f_name, [])
+            if len(candidates) != 1:
+                continue
+            data.metadata[SOURCE_LOCATION_METADATA] = {
+                "file": str(source_path.resolve()),
+                "line": candidates[0].lineno,
+                "column": candidates[0].col_offset + 1,
+            }
+
     def _get_module(
         self,
         py_module: types.ModuleType,
@@ -54,6 +93,58 @@ class GuppyCompiler:
         return module
 
 
+@contextlib.contextmanager
+def _record_node_locations(source_path: Path) -> Iterator[None]:
+    """Attach the span of the Python syntax being compiled to each new HUGR node.
+
+    Guppy compiles expressions and statements with `AstVisitor`s, so the
+    innermost visited node with a span is the origin of the HUGR nodes added
+    meanwhile. Builders may bypass `Hugr.add_node`, so the private method
+    creating the nodes is wrapped when it exists.
+    """
+    visiting: list[ast.AST] = []
+    visit = AstVisitor.visit
+    add_node_method = "_add_node" if hasattr(Hugr, "_add_node") else "add_node"
+    add_node = getattr(Hugr, add_node_method)
+
+    def located_visit(self: AstVisitor, node: ast.AST, *args: Any, **kwargs: Any) -> Any:
+        visiting.append(node)
+        try:
+            return visit(self, node, *args, **kwargs)
+        finally:
+            visiting.pop()
+
+    def located_add_node(self: Hugr, *args: Any, **kwargs: Any) -> Any:
+        node = add_node(self, *args, **kwargs)
+        location = next(
+            (loc for loc in map(_location, reversed(visiting)) if loc is not None), None
+        )
+        if location is not None:
+            location.setdefault("file", str(source_path.resolve()))
+            self[node].metadata.setdefault(SOURCE_LOCATION_METADATA, location)
+        return node
+
+    AstVisitor.visit = located_visit
+    setattr(Hugr, add_node_method, located_add_node)
+    try:
+        yield
+    finally:
+        AstVisitor.visit = visit
+        setattr(Hugr, add_node_method, add_node)
+
+
+def _location(node: ast.AST) -> dict[str, Any] | None:
+    """The start of a syntax node's span, if it has one."""
+    try:
+        start = to_span(node).start
+    except (AttributeError, TypeError, ValueError):
+        return None
+    location: dict[str, Any] = {"line": start.line, "column": start.column + 1}
+    if start.file:
+        location["file"] = str(Path(start.file).resolve())
+    return location
+
+
 class GuppyCompilerError(Exception):
     """Base class for Guppy compiler errors."""
 
@@ -0,0 +1,144 @@
+//! Lints for linearity violations in a HUGR.
+
+use std::fmt;
+
+use hugr::extension::prelude::qb_t;
+use hugr::ops::NamedOp;
+use hugr::types::{Type, TypeArg, TypeEnum};
+use hugr::{HugrView, NodeIndex, PortIndex};
+use serde::Serialize;
+
+use crate::cli::{CheckArgs, ReportFormat};
+use crate::compile::hugr::source::{self, SourceLocation};
+use crate::inspect::resources::{MEASURE_OPS, quantum_op_name};
+
+impl CheckArgs {
+    /// Run the `check` subcommand, printing the problems found.
+    ///
+    /// HUGR inputs are not validated, as the lint reports problems that
+    /// validation would reject.
+    ///
+    /// Returns an error if any problem was found.
+    pub fn run(&self) -> anyhow::Result<()> {
+        let stage = self
+            .input
+            .load_unvalidated_hugr(&self.guppy_version, &self.extensions)?;
+        let findings = check_linearity(stage.hugr());
+
+        match self.format {
+            ReportFormat::Text => {
+                for finding in &findings {
+                    println!("{finding}");
+                }
+            }
+            ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&findings)?),
+        }
+
+        if !findings.is_empty() {
+            anyhow::bail!("Found {} linearity problems", findings.len());
+        }
+        Ok(())
+    }
+}
+
+/// A problem found by the linearity lint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    /// The kind of problem.
+    pub kind: FindingKind,
+    /// The node producing the offending value.
+    pub node: usize,
+    /// The operation of the node.
+    pub op: String,
+    /// The output port of the offending value.
+    pub port: usize,
+    /// The source location of the node, if known.
+    pub source: Option<SourceLocation>,
+}
+
+/// Kinds of linearity problems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, strum::Display)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    /// A qubit is dropped without being measured or discarded.
+    #[strum(to_string = "qubit is never measured or discarded")]
+    QubitLeak,
+    /// A non-copyable value is consumed more than once.
+    #[strum(to_string = "linear value is consumed more than once")]
+    DoubleUse,
+    /// A measurement result is never used.
+    #[strum(to_string = "measurement result is never used")]
+    UnusedMeasurement,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: output {} of {} (n{})",
+            self.kind, self.port, self.op, self.node
+        )?;
+        if let Some(source) = &self.source {
+            write!(f, " at {source}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Find qubits that are never consumed, linear values consumed more than once,
+/// and measurement results that are never used.
+///
+/// Values holding qubits, such as arrays or tuples of qubits, count as qubits.
+pub fn check_linearity(hugr: &impl HugrView) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for node in hugr.nodes() {
+        let op = hugr.get_optype(node);
+        let is_measurement =
+            quantum_op_name(op).is_some_and(|name| MEASURE_OPS.contains(&name.as_str()));
+
+        for (port, ty) in hugr.out_value_types(node) {
+            let uses = hugr.linked_inputs(node, port).count();
+            let has_qubits = contains_qubit(&ty);
+            let kind = if has_qubits && uses == 0 {
+                Some(FindingKind::QubitLeak)
+            } else if !ty.copyable() && uses > 1 {
+                Some(FindingKind::DoubleUse)
+            } else if is_measurement && !has_qubits && uses == 0 {
+                Some(FindingKind::UnusedMeasurement)
+            } else {
+                None
+            };
+            if let Some(kind) = kind {
+                findings.push(Finding {
+                    kind,
+                    node: node.index(),
+                    op: op.name().to_string(),
+                    port: port.index(),
+                    source: source::source_location(hugr, node),
+                });
+            }
+        }
+    }
+
+    findings
+}
+
+/// Returns `true` if a value of the type holds qubits, directly or nested in
+/// arrays, tuples or sums.
+fn contains_qubit(ty: &Type) -> bool {
+    match ty.as_type_enum() {
+        TypeEnum::Extension(custom) => {
+            *ty == qb_t()
+                || custom.args().iter().any(|arg| match arg {
+                    TypeArg::Type { ty } => contains_qubit(ty),
+                    _ => false,
+                })
+        }
+        TypeEnum::Sum(sum) => (0..sum.num_variants())
+            .filter_map(|tag| sum.get_variant(tag))
+            .flat_map(|row| row.iter())
+            .any(|ty| Type::try_from(ty.clone()).is_ok_and(|ty| contains_qubit(&ty))),
+        _ => false,
+    }
+}
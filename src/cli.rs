@@ -1,29 +1,70 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use clap::{Args, Parser, ValueEnum, crate_version};
+use clap::{Args, Parser, Subcommand, ValueEnum, crate_version};
 use clap_verbosity_flag::{InfoLevel, Verbosity};
-use hugr::package::Package;
 use semver::Version;
 
 use crate::compile::guppy::GuppyStage;
 use crate::compile::hugr::HugrStage;
+use crate::compile::hugr::passes::HugrPass;
+use crate::compile::llvm::LLVMStage;
+use crate::compile::llvm::qir::QirProfile;
 use crate::compile::{CompilationStage, GenericStage, Stage};
 
 /// CLI arguments.
 #[derive(Parser, Debug)]
 #[clap(version = crate_version!(), long_about = None)]
 #[clap(about = "Guppy compilation tools.")]
+#[clap(subcommand_negates_reqs = true)]
 #[non_exhaustive]
 pub struct CliArgs {
+    /// Subcommand to run instead of compiling the input.
+    #[clap(subcommand)]
+    pub command: Option<Command>,
     /// Input file.
     #[clap(flatten)]
     pub input: InputFile,
     /// Function name to use as entrypoint.
+    ///
+    /// Defaults to `main`, if the program defines it.
+    ///
+    /// Nested functions can be selected with qualified names (`outer.inner`),
+    /// and overloaded names disambiguated with a node id (`main@n42` or `@n42`)
+    /// or a signature (`main: [] -> [bool]`). Signatures list the input and
+    /// output types, written `bool`, `int`, `float`, `qubit` or as printed by
+    /// HUGR.
+    ///
+    /// For LLVM inputs, this is the LLVM symbol of the function instead, used
+    /// as is, and there is no default.
     #[clap(short, long)]
     pub entrypoint: Option<String>,
     /// Optimisation level.
     #[clap(short, long, default_value = "2")]
     pub opt: OptimisationLevel,
+    /// Comma-separated list of HUGR passes to run, in order.
+    ///
+    /// Defaults to a pipeline chosen according to the optimisation level.
+    #[clap(long, value_delimiter = ',')]
+    pub hugr_passes: Option<Vec<HugrPass>>,
+    /// Check that the HUGR survives a round-trip through the hugr-model
+    /// S-expression and binary formats, failing on the first mismatching node.
+    #[clap(long)]
+    pub roundtrip_check: bool,
+    /// LLVM library to link into the output, as text (`.ll`) or bitcode (`.bc`).
+    ///
+    /// Can be repeated. Libraries are linked before the LLVM optimisations.
+    #[clap(long, value_name = "LIBRARY")]
+    pub link: Vec<PathBuf>,
+    /// Emit DWARF debug information locating the LLVM output in the guppy source.
+    ///
+    /// Quantum, rotation, futures and result operations are located at the
+    /// source they were compiled from, the instructions following them share
+    /// their location. Instructions before the first such operation of a
+    /// function point at its `def` line.
+    ///
+    /// Not available for LLVM inputs.
+    #[clap(short = 'g', long)]
+    pub debug_info: bool,
     /// Verbosity level.
     #[clap(flatten)]
     pub verbosity: Verbosity<InfoLevel>,
@@ -33,6 +74,111 @@ pub struct CliArgs {
     /// Guppy language version to use.
     #[clap(flatten)]
     pub guppy_version: GuppyVersion,
+    /// Additional extension definitions.
+    #[clap(flatten)]
+    pub extensions: ExtensionArgs,
+    /// Target machine for the LLVM output.
+    #[clap(flatten)]
+    pub target: TargetArgs,
+}
+
+/// Subcommands of the CLI.
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Report information about a program.
+    Inspect(InspectArgs),
+    /// Compare the functions in two HUGR packages.
+    Diff(DiffArgs),
+    /// Check a program for qubit leaks and other linearity violations.
+    Check(CheckArgs),
+    /// Check an LLVM module against a QIR profile.
+    QirCheck(QirCheckArgs),
+}
+
+/// Arguments for the `inspect` subcommand.
+///
+/// By default, lists the modules, functions and extensions in the package.
+#[derive(Args, Debug, Clone)]
+pub struct InspectArgs {
+    /// Input file.
+    #[clap(flatten)]
+    pub input: InputFile,
+    /// Function to use as entrypoint when summarising the call graph.
+    ///
+    /// Accepts the same formats as the compiler's `--entrypoint`.
+    #[clap(short, long)]
+    pub entrypoint: Option<String>,
+    /// Report the quantum resources used by each function, instead of the
+    /// package contents.
+    #[clap(long)]
+    pub resources: bool,
+    /// Output format of the report.
+    #[clap(long, default_value = "text")]
+    pub format: ReportFormat,
+    /// Guppy language version to use.
+    #[clap(flatten)]
+    pub guppy_version: GuppyVersion,
+    /// Additional extension definitions.
+    #[clap(flatten)]
+    pub extensions: ExtensionArgs,
+}
+
+/// Arguments for the `diff` subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct DiffArgs {
+    /// The original `.hugr` file.
+    pub before: PathBuf,
+    /// The modified `.hugr` file.
+    pub after: PathBuf,
+    /// Also compare the matched functions node by node.
+    #[clap(long)]
+    pub nodes: bool,
+    /// Output format of the report.
+    #[clap(long, default_value = "text")]
+    pub format: ReportFormat,
+    /// Additional extension definitions.
+    #[clap(flatten)]
+    pub extensions: ExtensionArgs,
+}
+
+/// Arguments for the `check` subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct CheckArgs {
+    /// Input file.
+    #[clap(flatten)]
+    pub input: InputFile,
+    /// Output format of the report.
+    #[clap(long, default_value = "text")]
+    pub format: ReportFormat,
+    /// Guppy language version to use.
+    #[clap(flatten)]
+    pub guppy_version: GuppyVersion,
+    /// Additional extension definitions.
+    #[clap(flatten)]
+    pub extensions: ExtensionArgs,
+}
+
+/// Arguments for the `qir-check` subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct QirCheckArgs {
+    /// The LLVM module to check, as text (`.ll`) or bitcode (`.bc`).
+    pub input: PathBuf,
+    /// The QIR profile to check against.
+    #[clap(long)]
+    pub profile: QirProfile,
+    /// Output format of the report.
+    #[clap(long, default_value = "text")]
+    pub format: ReportFormat,
+}
+
+/// Output format for reports.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    /// Human-readable text.
+    #[default]
+    Text,
+    /// JSON.
+    Json,
 }
 
 /// Input format options
@@ -40,11 +186,44 @@ pub struct CliArgs {
 #[group(multiple = false, required = true)]
 pub struct InputFile {
     /// A guppy program definition.
+    ///
+    /// Files ending in `.hugr`, `.sexpr` or `.hugrb` are loaded as HUGR JSON
+    /// packages, hugr-model S-expressions or hugr-model binaries respectively,
+    /// and files ending in `.ll` or `.bc` as LLVM IR or bitcode.
     #[clap(name = "input", help_heading = "Input format")]
     pub guppy_input: Option<PathBuf>,
     /// A `.hugr` file.
     #[clap(long, help_heading = "Input format")]
     pub hugr_input: Option<PathBuf>,
+    /// A hugr-model S-expression file.
+    #[clap(long, help_heading = "Input format")]
+    pub sexpr_input: Option<PathBuf>,
+    /// A hugr-model binary file.
+    #[clap(long, help_heading = "Input format")]
+    pub hugr_binary_input: Option<PathBuf>,
+    /// An LLVM IR text file.
+    #[clap(long, help_heading = "Input format")]
+    pub llvm_input: Option<PathBuf>,
+    /// An LLVM bitcode file.
+    #[clap(long, help_heading = "Input format")]
+    pub bitcode_input: Option<PathBuf>,
+}
+
+/// Formats accepted as input to the compiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display)]
+pub enum InputKind {
+    /// A guppy program definition.
+    Guppy,
+    /// A HUGR package encoded as JSON.
+    HugrJson,
+    /// A HUGR module encoded as a hugr-model S-expression.
+    HugrSexpr,
+    /// A HUGR module encoded in the hugr-model binary format.
+    HugrBinary,
+    /// An LLVM module in the text IR format.
+    LlvmText,
+    /// An LLVM module in the bitcode format.
+    LlvmBitcode,
 }
 
 /// Output format options
@@ -56,15 +235,69 @@ pub struct OutputFormat {
     /// Store the intermediate HUGR as an S-expression.
     #[clap(long, help_heading = "Output artifacts")]
     pub sexpr: Option<PathBuf>,
+    /// Store the intermediate HUGR in the hugr-model binary format.
+    #[clap(long, help_heading = "Output artifacts")]
+    pub hugr_binary: Option<PathBuf>,
+    /// Store the HUGR artifacts after running the HUGR passes, as fed into the
+    /// LLVM lowering, instead of before.
+    #[clap(long, help_heading = "Output artifacts")]
+    pub hugr_after_passes: bool,
+    /// Store the HUGR as json after each run of the given pass.
+    ///
+    /// Can be repeated or comma-separated. Files are named after the pass
+    /// position in the pipeline and its name.
+    #[clap(long, value_delimiter = ',', help_heading = "Output artifacts")]
+    pub dump_hugr_after: Vec<HugrPass>,
+    /// Directory where the `--dump-hugr-after` files are stored.
+    #[clap(long, default_value = ".", help_heading = "Output artifacts")]
+    pub dump_dir: PathBuf,
     /// Store the mermaid diagram for the HUGR.
     #[clap(short, long, help_heading = "Output artifacts")]
     pub mermaid: Option<PathBuf>,
+    /// Store a Graphviz DOT diagram for the HUGR.
+    #[clap(long, help_heading = "Output artifacts")]
+    pub dot: Option<PathBuf>,
+    /// Store an interactive HTML view of the HUGR.
+    #[clap(long, help_heading = "Output artifacts")]
+    pub html: Option<PathBuf>,
+    /// Only include the given function in the DOT and HTML views.
+    #[clap(long, help_heading = "Output artifacts")]
+    pub view_function: Option<String>,
+    /// Collapse regions nested deeper than this in the DOT and HTML views.
+    #[clap(long, help_heading = "Output artifacts")]
+    pub view_depth: Option<usize>,
+    /// Store a function and its transitive callees as a separate `.hugr` package.
+    ///
+    /// Can be repeated to extract multiple functions, each to its own file.
+    #[clap(long, help_heading = "Output artifacts")]
+    pub extract: Vec<String>,
+    /// Directory where the extracted functions are stored.
+    #[clap(long, default_value = ".", help_heading = "Output artifacts")]
+    pub extract_dir: PathBuf,
     /// LLVM IR (text) output
     #[clap(short, long, help_heading = "Output artifacts")]
     pub llvm: Option<PathBuf>,
     /// LLVM Bitcode output
     #[clap(short, long, help_heading = "Output artifacts")]
     pub bitcode: Option<PathBuf>,
+    /// Object file output, for the selected target.
+    #[clap(long, help_heading = "Output artifacts")]
+    pub object: Option<PathBuf>,
+    /// Assembly output, for the selected target.
+    #[clap(long, help_heading = "Output artifacts")]
+    pub asm: Option<PathBuf>,
+    /// Store a JSON manifest mapping the HUGR functions to their LLVM symbols.
+    #[clap(long, help_heading = "Output artifacts")]
+    pub manifest: Option<PathBuf>,
+    /// Stable symbol name to give the entrypoint in the LLVM output, instead
+    /// of its mangled name.
+    #[clap(long, help_heading = "Output artifacts")]
+    pub export_name: Option<String>,
+    /// Store the LLVM IR even if it fails verification, for debugging.
+    ///
+    /// Invalid modules are not optimised, and no object file or assembly is produced.
+    #[clap(long, help_heading = "Output artifacts")]
+    pub emit_invalid: bool,
 }
 
 /// Argument to specify the guppy language version, either using semver or a git ref.
@@ -88,6 +321,44 @@ pub struct GuppyVersion {
     pub guppy_ref: Option<String>,
 }
 
+/// Arguments to load extension definitions in addition to the standard ones.
+#[derive(Args, Debug, Default, Clone)]
+pub struct ExtensionArgs {
+    /// A serialized extension definition, in JSON.
+    #[clap(long, help_heading = "Extensions")]
+    pub extension: Vec<PathBuf>,
+    /// A directory containing serialized extension definitions, as `.json` files.
+    #[clap(long, help_heading = "Extensions")]
+    pub extension_dir: Vec<PathBuf>,
+}
+
+/// Arguments to select the target machine of the LLVM output.
+#[derive(Args, Debug, Default, Clone)]
+pub struct TargetArgs {
+    /// Target triple, e.g. `aarch64-unknown-linux-gnu`.
+    ///
+    /// Defaults to the host. LLVM inputs keep the triple and data layout they
+    /// declare, unless a target, CPU or features are given.
+    #[clap(long, help_heading = "Target")]
+    pub target: Option<String>,
+    /// Target CPU.
+    ///
+    /// Defaults to the host CPU, or `generic` when a target is given.
+    #[clap(long, help_heading = "Target")]
+    pub cpu: Option<String>,
+    /// Target features, e.g. `+neon,-sve`.
+    ///
+    /// Defaults to the host features, or none when a target is given.
+    #[clap(long, help_heading = "Target")]
+    pub features: Option<String>,
+    /// QIR profile the LLVM output must conform to.
+    ///
+    /// The program is transformed to fit the profile, or rejected with an
+    /// explanation if it cannot be.
+    #[clap(long, help_heading = "Target")]
+    pub qir_profile: Option<QirProfile>,
+}
+
 /// Optimisation level.
 #[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum OptimisationLevel {
@@ -108,6 +379,10 @@ pub enum OptimisationLevel {
 impl CliArgs {
     /// Run the CLI.
     pub fn run(&self) -> anyhow::Result<()> {
+        if let Some(command) = &self.command {
+            return command.run();
+        }
+
         self.validate()?;
 
         let mut stage = self.init_stage()?;
@@ -125,22 +400,132 @@ impl CliArgs {
     /// Validate the CLI arguments.
     pub fn validate(&self) -> anyhow::Result<()> {
         self.guppy_version.validate()?;
+        let (kind, _) = self.input.source()?;
+        if kind.is_llvm() && Stage::Hugr.required(self) {
+            anyhow::bail!("Cannot produce HUGR artifacts from {kind} input");
+        }
+        if kind.is_llvm() && self.debug_info {
+            anyhow::bail!("Cannot emit debug information for {kind} input");
+        }
         Ok(())
     }
 
     /// Returns the initial stage based on the input file.
     pub fn init_stage(&self) -> anyhow::Result<GenericStage> {
-        if let Some(guppy_input) = &self.input.guppy_input {
-            Ok(GuppyStage::new(&self.guppy_version, guppy_input).wrap())
-        } else if let Some(hugr_input) = &self.input.hugr_input {
-            let pkg = Package::from_json_file(hugr_input, &hugr::std_extensions::std_reg())?;
-            Ok(HugrStage { pkg }.wrap())
+        match self.input.source()? {
+            (kind, path) if kind.is_llvm() => Ok(LLVMStage::from_file(path, kind, self)?.wrap()),
+            _ => self.input.init_stage(&self.guppy_version, &self.extensions),
+        }
+    }
+}
+
+impl Command {
+    /// Run the subcommand.
+    pub fn run(&self) -> anyhow::Result<()> {
+        match self {
+            Command::Inspect(args) => args.run(),
+            Command::Diff(args) => args.run(),
+            Command::Check(args) => args.run(),
+            Command::QirCheck(args) => args.run(),
+        }
+    }
+}
+
+impl InputFile {
+    /// Returns the initial compilation stage for the input file.
+    pub fn init_stage(
+        &self,
+        guppy_version: &GuppyVersion,
+        extensions: &ExtensionArgs,
+    ) -> anyhow::Result<GenericStage> {
+        let (kind, path) = self.source()?;
+        let stage = match kind {
+            InputKind::Guppy => GuppyStage::new(guppy_version, path).wrap(),
+            InputKind::HugrJson => HugrStage::from_file(path, &extensions.registry()?)?.wrap(),
+            InputKind::HugrSexpr => {
+                HugrStage::from_sexpr_file(path, &extensions.registry()?)?.wrap()
+            }
+            InputKind::HugrBinary => {
+                HugrStage::from_binary_file(path, &extensions.registry()?)?.wrap()
+            }
+            InputKind::LlvmText | InputKind::LlvmBitcode => {
+                anyhow::bail!("Expected a HUGR or guppy program, got {kind} input")
+            }
+        };
+        Ok(stage)
+    }
+
+    /// Load the input file as a HUGR, compiling it first if it is a guppy program.
+    pub fn load_hugr(
+        &self,
+        guppy_version: &GuppyVersion,
+        extensions: &ExtensionArgs,
+    ) -> anyhow::Result<HugrStage> {
+        guppy_version.validate()?;
+        match self.init_stage(guppy_version, extensions)? {
+            GenericStage::GuppyProgram(guppy) => guppy.compile_hugr(&extensions.registry()?, false),
+            GenericStage::Hugr(hugr) => Ok(hugr),
+            other => anyhow::bail!("Expected a HUGR or guppy program, got {}", other.stage()),
+        }
+    }
+
+    /// Load the input file as a HUGR like [InputFile::load_hugr], without
+    /// validating HUGR JSON inputs.
+    pub fn load_unvalidated_hugr(
+        &self,
+        guppy_version: &GuppyVersion,
+        extensions: &ExtensionArgs,
+    ) -> anyhow::Result<HugrStage> {
+        match self.source()? {
+            (InputKind::HugrJson, path) => {
+                HugrStage::from_file_unvalidated(path, &extensions.registry()?)
+            }
+            _ => self.load_hugr(guppy_version, extensions),
+        }
+    }
+
+    /// Returns the input path along with its format.
+    ///
+    /// The format of the positional input is inferred from its extension,
+    /// defaulting to a guppy program.
+    pub fn source(&self) -> anyhow::Result<(InputKind, &Path)> {
+        if let Some(input) = &self.guppy_input {
+            Ok((InputKind::from_extension(input), input))
+        } else if let Some(hugr_input) = &self.hugr_input {
+            Ok((InputKind::HugrJson, hugr_input))
+        } else if let Some(sexpr_input) = &self.sexpr_input {
+            Ok((InputKind::HugrSexpr, sexpr_input))
+        } else if let Some(binary_input) = &self.hugr_binary_input {
+            Ok((InputKind::HugrBinary, binary_input))
+        } else if let Some(llvm_input) = &self.llvm_input {
+            Ok((InputKind::LlvmText, llvm_input))
+        } else if let Some(bitcode_input) = &self.bitcode_input {
+            Ok((InputKind::LlvmBitcode, bitcode_input))
         } else {
             anyhow::bail!("No input file specified")
         }
     }
 }
 
+impl InputKind {
+    /// Guess the input format from a file extension.
+    pub fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("hugr") => InputKind::HugrJson,
+            Some("sexpr") => InputKind::HugrSexpr,
+            Some("hugrb") => InputKind::HugrBinary,
+            Some("ll") => InputKind::LlvmText,
+            Some("bc") => InputKind::LlvmBitcode,
+            _ => InputKind::Guppy,
+        }
+    }
+
+    /// Returns `true` if the input is an LLVM module.
+    pub fn is_llvm(&self) -> bool {
+        matches!(self, InputKind::LlvmText | InputKind::LlvmBitcode)
+    }
+}
+
 impl GuppyVersion {
     /// Check that no incompatible options are set.
     pub fn validate(&self) -> anyhow::Result<()> {
@@ -53,8 +53,24 @@ impl Stage {
         let out = &args.output;
         match self {
             Stage::GuppyProgram => true,
-            Stage::Hugr => out.hugr.is_some() || out.mermaid.is_some() || out.sexpr.is_some(),
-            Stage::LLVM => out.llvm.is_some() || out.bitcode.is_some(),
+            Stage::Hugr => {
+                out.hugr.is_some()
+                    || out.mermaid.is_some()
+                    || out.sexpr.is_some()
+                    || out.hugr_binary.is_some()
+                    || out.dot.is_some()
+                    || out.html.is_some()
+                    || !out.extract.is_empty()
+                    || !out.dump_hugr_after.is_empty()
+                    || args.roundtrip_check
+            }
+            Stage::LLVM => {
+                out.llvm.is_some()
+                    || out.bitcode.is_some()
+                    || out.object.is_some()
+                    || out.asm.is_some()
+                    || out.manifest.is_some()
+            }
         }
     }
 
@@ -2,6 +2,7 @@
 
 use std::path::{Path, PathBuf};
 
+use hugr::extension::ExtensionRegistry;
 use itertools::Itertools;
 
 use crate::cli::{CliArgs, GuppyVersion};
@@ -30,6 +31,27 @@ impl CompilationStage for GuppyStage {
     }
 
     fn compile(self, args: &CliArgs) -> anyhow::Result<GenericStage> {
+        let registry = args.extensions.registry()?;
+        Ok(self
+            .compile_hugr(&registry, args.verbosity.is_silent())?
+            .wrap())
+    }
+
+    fn store(&self, _args: &crate::cli::CliArgs) -> anyhow::Result<()> {
+        // Nothing to store.
+        Ok(())
+    }
+}
+
+impl GuppyStage {
+    /// Compile the guppy program into a HUGR.
+    ///
+    /// Unless `silent` is set, errors reported by guppy are printed to stdout.
+    pub fn compile_hugr(
+        &self,
+        registry: &ExtensionRegistry,
+        silent: bool,
+    ) -> anyhow::Result<HugrStage> {
         // Execute the guppy compilation script using uv to set the guppylang version.
         // This will output the HUGR json file.
 
@@ -50,7 +72,7 @@ impl CompilationStage for GuppyStage {
         if !output.status.success() {
             let guppy_err = String::from_utf8_lossy(&output.stderr);
 
-            if !args.verbosity.is_silent() {
+            if !silent {
                 println!("{guppy_err}");
             }
 
@@ -62,16 +84,9 @@ impl CompilationStage for GuppyStage {
 
         let stdout = String::from_utf8(output.stdout)?;
 
-        Ok(HugrStage::from_json(stdout)?.wrap())
-    }
-
-    fn store(&self, _args: &crate::cli::CliArgs) -> anyhow::Result<()> {
-        // Nothing to store.
-        Ok(())
+        HugrStage::from_json(stdout, registry)
     }
-}
 
-impl GuppyStage {
     /// Returns a new GuppyStage with the given version and path.
     pub fn new(guppy_version: &GuppyVersion, path: impl AsRef<Path>) -> Self {
         Self {
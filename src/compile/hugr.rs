@@ -1,8 +1,9 @@
 //! Hugr IR stage.
 
 use derive_more::{Display, Error};
+use hugr::extension::ExtensionRegistry;
 use hugr::package::Package;
-use hugr::{Hugr, HugrView, Node, ops};
+use hugr::{Hugr, HugrView, Node, NodeIndex};
 use std::{fs, io, mem};
 
 use crate::cli::CliArgs;
@@ -10,6 +11,20 @@ use crate::cli::CliArgs;
 use super::llvm::LLVMStage;
 use super::{CompilationStage, GenericStage, Stage};
 
+pub mod compare;
+pub mod extract;
+pub mod functions;
+pub mod passes;
+pub mod render;
+pub mod source;
+
+use functions::{EntrypointSignatureError, FunctionSpec, SignatureSpec};
+use passes::HugrPass;
+use render::RenderOptions;
+
+/// Name of the function used as entrypoint when none is specified.
+pub const DEFAULT_ENTRYPOINT: &str = "main";
+
 /// A hugr IR object.
 #[derive(Debug, Clone)]
 pub struct HugrStage {
@@ -27,16 +42,49 @@ impl CompilationStage for HugrStage {
 
     fn compile(mut self, args: &CliArgs) -> anyhow::Result<GenericStage> {
         log::debug!("Compiling Hugr to LLVM IR");
-        let entrypoint = match &args.entrypoint {
-            Some(fn_name) => Some(self.find_funcdef_node(fn_name)?),
-            None => None,
-        };
-        self.guppy_pass(entrypoint)?;
+        let entrypoint = self.entrypoint(args.entrypoint.as_deref())?;
+        self.run_passes(entrypoint, args)?;
+        if let Some(profile) = args.target.qir_profile {
+            profile.prepare_hugr(self.hugr_mut(), entrypoint)?;
+        }
+        if args.output.hugr_after_passes {
+            self.store_artifacts(args)?;
+        }
         let hugr = mem::take(&mut self.pkg.modules[0]);
         Ok(LLVMStage::from_hugr(hugr, entrypoint, args)?.wrap())
     }
 
     fn store(&self, args: &crate::cli::CliArgs) -> anyhow::Result<()> {
+        if args.roundtrip_check {
+            self.roundtrip_check(&args.extensions.registry()?)?;
+        }
+
+        let out = &args.output;
+        if Stage::LLVM.required(args) {
+            if out.hugr_after_passes {
+                // The artifacts are stored by `compile`, once the passes have run.
+                return Ok(());
+            }
+            return self.store_artifacts(args);
+        }
+        if !out.hugr_after_passes && out.dump_hugr_after.is_empty() {
+            return self.store_artifacts(args);
+        }
+        // The passes are not run during compilation, so run them on a copy.
+        let mut processed = self.clone();
+        let entrypoint = processed.entrypoint(args.entrypoint.as_deref())?;
+        processed.run_passes(entrypoint, args)?;
+        if out.hugr_after_passes {
+            processed.store_artifacts(args)
+        } else {
+            self.store_artifacts(args)
+        }
+    }
+}
+
+impl HugrStage {
+    /// Store the HUGR artifacts requested by the CLI arguments.
+    fn store_artifacts(&self, args: &CliArgs) -> anyhow::Result<()> {
         let out = &args.output;
 
         if let Some(mermaid_out) = &out.mermaid {
@@ -45,6 +93,38 @@ impl CompilationStage for HugrStage {
             fs::write(mermaid_out, mermaid)?;
         }
 
+        if out.dot.is_some() || out.html.is_some() {
+            let root = match &out.view_function {
+                Some(fn_ref) => self.find_funcdef_node(fn_ref)?,
+                None => self.hugr().root(),
+            };
+            let options = RenderOptions {
+                root,
+                max_depth: out.view_depth,
+            };
+            if let Some(dot_out) = &out.dot {
+                log::debug!("Storing Graphviz output to {}", dot_out.display());
+                fs::write(dot_out, render::dot_string(self.hugr(), &options))?;
+            }
+            if let Some(html_out) = &out.html {
+                log::debug!("Storing HTML output to {}", html_out.display());
+                fs::write(html_out, render::html_string(self.hugr(), &options))?;
+            }
+        }
+
+        for fn_ref in &out.extract {
+            let func = self.find_funcdef_node(fn_ref)?;
+            let name = functions::functions(self.hugr())
+                .into_iter()
+                .find(|f| f.node == func)
+                .map_or_else(|| fn_ref.clone(), |f| f.qualified_name);
+            let path = out.extract_dir.join(extract::extracted_file_name(&name));
+            log::debug!("Storing extracted function {name} to {}", path.display());
+            let pkg = extract::extract_function(self.hugr(), func)?;
+            let writer = io::BufWriter::new(fs::File::create(path)?);
+            pkg.to_json_writer(writer)?;
+        }
+
         if let Some(hugr_out) = &out.hugr {
             log::debug!("Storing Hugr output to {}", hugr_out.display());
             let file = fs::File::create(hugr_out)?;
@@ -63,23 +143,152 @@ impl CompilationStage for HugrStage {
             fs::write(hugr_sexpr_out, sexpr)?;
         }
 
+        if let Some(hugr_binary_out) = &out.hugr_binary {
+            log::debug!(
+                "Storing Hugr binary output to {}",
+                hugr_binary_out.display()
+            );
+            fs::write(hugr_binary_out, self.to_binary())?;
+        }
+
         Ok(())
     }
-}
 
-impl HugrStage {
     /// Load a HugrStage from a JSON string.
-    pub fn from_json(json: impl AsRef<str>) -> anyhow::Result<Self> {
-        let pkg = Package::from_json(json, &hugr::std_extensions::std_reg())?;
+    pub fn from_json(json: impl AsRef<str>, registry: &ExtensionRegistry) -> anyhow::Result<Self> {
+        let pkg = Package::from_json(json, registry)?;
         Ok(Self { pkg })
     }
 
     /// Load a HugrStage from a JSON file.
-    pub fn from_file(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
-        let pkg = Package::from_json_file(path, &hugr::std_extensions::std_reg())?;
+    pub fn from_file(
+        path: impl AsRef<std::path::Path>,
+        registry: &ExtensionRegistry,
+    ) -> anyhow::Result<Self> {
+        let pkg = Package::from_json_file(path, registry)?;
+        Ok(Self { pkg })
+    }
+
+    /// Load a HugrStage from a JSON file without validating it.
+    ///
+    /// The operations are only resolved against the extensions in the
+    /// registry and those declared by the package, so that malformed HUGRs
+    /// can still be linted.
+    pub fn from_file_unvalidated(
+        path: impl AsRef<std::path::Path>,
+        registry: &ExtensionRegistry,
+    ) -> anyhow::Result<Self> {
+        #[derive(serde::Deserialize)]
+        struct PackageJson {
+            modules: Vec<Hugr>,
+            #[serde(default)]
+            extensions: Vec<hugr::Extension>,
+        }
+
+        let path = path.as_ref();
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let (mut modules, declared) = match serde_json::from_value::<PackageJson>(json.clone()) {
+            Ok(pkg) => (pkg.modules, pkg.extensions),
+            // A bare HUGR, outside of a package.
+            Err(_) => (vec![serde_json::from_value::<Hugr>(json)?], Vec::new()),
+        };
+
+        let mut registry = registry.clone();
+        for ext in declared {
+            registry.register_updated(std::sync::Arc::new(ext));
+        }
+        for module in &mut modules {
+            module
+                .resolve_extension_defs(&registry)
+                .map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+        }
+        let pkg = Package::new(modules)?;
         Ok(Self { pkg })
     }
 
+    /// Load a HugrStage from a hugr-model S-expression.
+    pub fn from_sexpr(
+        sexpr: impl AsRef<str>,
+        registry: &ExtensionRegistry,
+    ) -> anyhow::Result<Self> {
+        let bump = bumpalo::Bump::new();
+        let parsed = hugr_model::v0::text::parse(sexpr.as_ref(), &bump)
+            .map_err(|e| anyhow::anyhow!("Failed to parse the HUGR S-expression.\n{e}"))?;
+        let hugr = hugr_core::import::import_hugr(&parsed.module, registry)?;
+        let pkg = Package::new([hugr])?;
+        Ok(Self { pkg })
+    }
+
+    /// Load a HugrStage from a hugr-model S-expression file.
+    pub fn from_sexpr_file(
+        path: impl AsRef<std::path::Path>,
+        registry: &ExtensionRegistry,
+    ) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let sexpr = fs::read_to_string(path)?;
+        Self::from_sexpr(sexpr, registry).map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))
+    }
+
+    /// Load a HugrStage from the hugr-model binary encoding.
+    pub fn from_binary(bytes: &[u8], registry: &ExtensionRegistry) -> anyhow::Result<Self> {
+        let bump = bumpalo::Bump::new();
+        let module = hugr_model::v0::binary::read_from_slice(bytes, &bump)?;
+        let hugr = hugr_core::import::import_hugr(&module, registry)?;
+        let pkg = Package::new([hugr])?;
+        Ok(Self { pkg })
+    }
+
+    /// Load a HugrStage from a hugr-model binary file.
+    pub fn from_binary_file(
+        path: impl AsRef<std::path::Path>,
+        registry: &ExtensionRegistry,
+    ) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+        Self::from_binary(&bytes, registry).map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))
+    }
+
+    /// Encode the [Hugr] in the package using the hugr-model binary format.
+    pub fn to_binary(&self) -> Vec<u8> {
+        let bump = bumpalo::Bump::new();
+        let model = hugr_core::export::export_hugr(self.hugr(), &bump);
+        hugr_model::v0::binary::write_to_vec(&model)
+    }
+
+    /// Check that the [Hugr] in the package is preserved when exported to the
+    /// hugr-model formats and imported back.
+    ///
+    /// Fails with the first mismatching node.
+    pub fn roundtrip_check(&self, registry: &ExtensionRegistry) -> anyhow::Result<()> {
+        let bump = bumpalo::Bump::new();
+        let model = hugr_core::export::export_hugr(self.hugr(), &bump);
+        let sexpr = hugr_model::v0::text::print_to_string(&model, 120)?;
+
+        let roundtrips = [
+            ("S-expression", Self::from_sexpr(&sexpr, registry)),
+            ("binary", Self::from_binary(&self.to_binary(), registry)),
+        ];
+        for (format, imported) in roundtrips {
+            log::debug!("Checking the Hugr round-trip through the {format} format");
+            let imported = imported.map_err(|e| {
+                anyhow::anyhow!("Round-trip through the {format} format failed to import.\n{e}")
+            })?;
+            let (original, imported) = (self.hugr(), imported.hugr());
+            let diffs = compare::compare_regions(
+                original,
+                original.root(),
+                imported,
+                imported.root(),
+                compare::Precision::Exact,
+                Some(1),
+            );
+            if let Some(diff) = diffs.first() {
+                anyhow::bail!("Round-trip through the {format} format changed the Hugr: {diff}");
+            }
+        }
+        Ok(())
+    }
+
     /// Reference to the [Hugr] in the package.
     pub fn hugr(&self) -> &Hugr {
         &self.pkg.modules[0]
@@ -90,52 +299,102 @@ impl HugrStage {
         &mut self.pkg.modules[0]
     }
 
-    // Find the FuncDefn node for the function we're trying to execute in the package.
-    fn find_funcdef_node(&self, fn_name: &str) -> Result<Node, HugrToLlvmError> {
-        let root = self.hugr().root();
-        let mut fn_nodes = Vec::new();
+    /// Find the FuncDefn node for a function reference in the package.
+    ///
+    /// See [FunctionSpec] for the accepted formats.
+    pub fn find_funcdef_node(&self, fn_ref: &str) -> Result<Node, HugrToLlvmError> {
+        let spec: FunctionSpec = fn_ref
+            .parse()
+            .map_err(|msg| HugrToLlvmError::InvalidFunctionReference { msg })?;
+        let module = functions::module_name(self.hugr());
+        let defined = functions::functions(self.hugr())
+            .into_iter()
+            .filter(|f| !f.is_declaration)
+            .collect::<Vec<_>>();
 
-        // Return the function name of an operation, if it is a FuncDefn.
-        fn get_fn_name(op: &ops::OpType) -> Option<&str> {
-            match op {
-                ops::OpType::FuncDefn(ops::FuncDefn { name, .. }) => Some(name),
-                _ => None,
-            }
-        }
+        let fn_nodes = spec.select(&defined, module);
 
-        for n in self.hugr().children(root) {
-            let op = self.hugr().get_optype(n);
-            if get_fn_name(op) == Some(fn_name) {
-                fn_nodes.push(n);
+        match fn_nodes.as_slice() {
+            [] => {
+                let available: Vec<String> =
+                    defined.iter().map(|f| f.qualified_name.clone()).collect();
+                let suggestion = spec.name.as_ref().and_then(|name| {
+                    functions::closest_name(name, available.iter().map(String::as_str))
+                });
+                Err(HugrToLlvmError::MissingFunction {
+                    fn_name: spec.to_string(),
+                    available,
+                    suggestion,
+                })
             }
+            [f] => Ok(f.node),
+            _ => Err(HugrToLlvmError::MultipleFunctions {
+                fn_name: spec.to_string(),
+                candidates: fn_nodes
+                    .iter()
+                    .map(|f| {
+                        let signature = SignatureSpec::of(&f.signature);
+                        format!("{}@n{}: {signature}", f.qualified_name, f.node.index())
+                    })
+                    .collect(),
+            }),
         }
+    }
 
-        if fn_nodes.is_empty() {
-            let available = self
-                .hugr()
-                .children(root)
-                .filter_map(|n| get_fn_name(self.hugr().get_optype(n)))
-                .map(ToString::to_string)
-                .collect();
-            return Err(HugrToLlvmError::MissingFunction {
-                fn_name: fn_name.to_string(),
-                available,
-            });
-        }
-        if fn_nodes.len() > 1 {
-            return Err(HugrToLlvmError::MultipleFunctions {
-                fn_name: fn_name.to_string(),
-            });
+    /// Returns the entrypoint function, checking that its signature is valid.
+    ///
+    /// If no function is requested, a function named `main` is used when present.
+    pub fn entrypoint(&self, fn_ref: Option<&str>) -> Result<Option<Node>, HugrToLlvmError> {
+        let node = match fn_ref {
+            Some(fn_ref) => self.find_funcdef_node(fn_ref)?,
+            None => match self.find_funcdef_node(DEFAULT_ENTRYPOINT) {
+                Ok(node) => node,
+                Err(HugrToLlvmError::MissingFunction { .. }) => return Ok(None),
+                Err(e) => {
+                    log::warn!("Compiling without an entrypoint. {e}");
+                    return Ok(None);
+                }
+            },
+        };
+
+        let func = functions::functions(self.hugr())
+            .into_iter()
+            .find(|f| f.node == node)
+            .expect("The entrypoint is a function definition");
+        match func.check_entrypoint_signature() {
+            Ok(()) => {
+                log::debug!("Using {} as the entrypoint", func.qualified_name);
+                Ok(Some(node))
+            }
+            Err(reason) if fn_ref.is_none() => {
+                log::warn!(
+                    "Compiling without an entrypoint. Function {} is not a valid entrypoint: {reason}",
+                    func.qualified_name
+                );
+                Ok(None)
+            }
+            Err(reason) => Err(HugrToLlvmError::InvalidEntrypoint {
+                fn_name: func.qualified_name,
+                reason,
+            }),
         }
-        Ok(fn_nodes[0])
     }
 
-    fn guppy_pass(&mut self, entrypoint: Option<Node>) -> anyhow::Result<()> {
-        hugr::algorithms::MonomorphizePass::default().run(self.hugr_mut())?;
-        if let Some(entrypoint) = entrypoint {
-            hugr::algorithms::RemoveDeadFuncsPass::default()
-                .with_module_entry_points([entrypoint])
-                .run(self.hugr_mut())?
+    /// Run the HUGR passes selected by the CLI arguments, or the default
+    /// pipeline for the optimisation level.
+    fn run_passes(&mut self, entrypoint: Option<Node>, args: &CliArgs) -> anyhow::Result<()> {
+        let passes = match &args.hugr_passes {
+            Some(passes) => passes.clone(),
+            None => HugrPass::pipeline(args.opt),
+        };
+        for (i, pass) in passes.into_iter().enumerate() {
+            pass.run(self.hugr_mut(), entrypoint)?;
+            if args.output.dump_hugr_after.contains(&pass) {
+                let path = args.output.dump_dir.join(format!("{i:02}-{pass}.hugr"));
+                log::debug!("Storing Hugr after pass {pass} to {}", path.display());
+                let writer = io::BufWriter::new(fs::File::create(path)?);
+                self.pkg.to_json_writer(writer)?;
+            }
         }
         Ok(())
     }
@@ -146,15 +405,40 @@ impl HugrStage {
 pub enum HugrToLlvmError {
     /// The HUGR does not contain the function we're trying to compile.
     #[display(
-        "Cannot find function {fn_name} in the Hugr package. Available functions: {available:?}"
+        "Cannot find function {fn_name} in the Hugr package.{} Available functions: {available:?}",
+        suggestion.as_ref().map(|s| format!(" Did you mean {s}?")).unwrap_or_default()
     )]
     MissingFunction {
         /// The function name we were trying to compile.
         fn_name: String,
         /// The available function names.
         available: Vec<String>,
+        /// The available function name closest to the requested one.
+        suggestion: Option<String>,
     },
     /// The HUGR contains multiple functions with the same name.
-    #[display("Multiple functions with the name {fn_name} found in the Hugr package.")]
-    MultipleFunctions { fn_name: String },
+    #[display(
+        "Multiple functions with the name {fn_name} found in the Hugr package. Use a qualified name, node id or signature to choose between: {candidates:?}"
+    )]
+    MultipleFunctions {
+        /// The function name we were trying to compile.
+        fn_name: String,
+        /// The matching functions, with their node ids and signatures.
+        candidates: Vec<String>,
+    },
+    /// The requested function cannot be used as an entrypoint.
+    #[display("Function {fn_name} is not a valid entrypoint: {reason}")]
+    InvalidEntrypoint {
+        /// The function name.
+        fn_name: String,
+        /// Why the function is not a valid entrypoint.
+        #[error(source)]
+        reason: EntrypointSignatureError,
+    },
+    /// The function reference could not be parsed.
+    #[display("{msg}")]
+    InvalidFunctionReference {
+        /// The parsing error.
+        msg: String,
+    },
 }
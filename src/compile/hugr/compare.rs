@@ -0,0 +1,276 @@
+//! Structural comparison of HUGR regions.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use hugr::ops::{NamedOp, OpType};
+use hugr::{HugrView, IncomingPort, Node, NodeIndex, PortIndex};
+use serde::Serialize;
+
+use super::functions;
+
+/// A structural difference between two HUGR regions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NodeDifference {
+    /// Matching nodes have different operations.
+    Operation {
+        /// The node in the first HUGR.
+        left: usize,
+        /// The node in the second HUGR.
+        right: usize,
+        /// The operation in the first HUGR.
+        left_op: String,
+        /// The operation in the second HUGR.
+        right_op: String,
+    },
+    /// Matching nodes have operations with the same name, but different
+    /// parameters such as type arguments, signatures or constant values.
+    Parameters {
+        /// The node in the first HUGR.
+        left: usize,
+        /// The node in the second HUGR.
+        right: usize,
+        /// The operation name.
+        op: String,
+    },
+    /// Matching nodes have different metadata.
+    Metadata {
+        /// The node in the first HUGR.
+        left: usize,
+        /// The node in the second HUGR.
+        right: usize,
+        /// The first differing metadata key.
+        key: String,
+    },
+    /// Matching nodes have a different number of children.
+    Children {
+        /// The node in the first HUGR.
+        left: usize,
+        /// The node in the second HUGR.
+        right: usize,
+        /// Number of children in the first HUGR.
+        left_count: usize,
+        /// Number of children in the second HUGR.
+        right_count: usize,
+    },
+    /// An input port of matching nodes is connected to different sources.
+    Input {
+        /// The node in the first HUGR.
+        left: usize,
+        /// The node in the second HUGR.
+        right: usize,
+        /// The input port.
+        port: usize,
+    },
+}
+
+impl fmt::Display for NodeDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeDifference::Operation {
+                left,
+                right,
+                left_op,
+                right_op,
+            } => write!(f, "n{left} is {left_op} but n{right} is {right_op}"),
+            NodeDifference::Parameters { left, right, op } => write!(
+                f,
+                "n{left} and n{right} are both {op}, with different parameters"
+            ),
+            NodeDifference::Metadata { left, right, key } => {
+                write!(f, "metadata {key} of n{left} and n{right} differs")
+            }
+            NodeDifference::Children {
+                left,
+                right,
+                left_count,
+                right_count,
+            } => write!(
+                f,
+                "n{left} has {left_count} children but n{right} has {right_count}"
+            ),
+            NodeDifference::Input { left, right, port } => write!(
+                f,
+                "input {port} of n{left} and n{right} is connected to different nodes"
+            ),
+        }
+    }
+}
+
+/// How closely the nodes paired by [compare_regions] are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    /// Compare the operation names, including function names.
+    Labels,
+    /// Compare the full operations, including their type arguments,
+    /// signatures and constant values, and the node metadata.
+    Exact,
+}
+
+/// Compare the regions rooted at `left_root` and `right_root`, pairing the
+/// nodes of the hierarchies in order.
+///
+/// Stops after finding `limit` differences, if given.
+pub fn compare_regions(
+    left: &impl HugrView,
+    left_root: Node,
+    right: &impl HugrView,
+    right_root: Node,
+    precision: Precision,
+    limit: Option<usize>,
+) -> Vec<NodeDifference> {
+    let full = |diffs: &Vec<NodeDifference>| limit.is_some_and(|l| diffs.len() >= l);
+    let mut diffs = Vec::new();
+
+    // Pair the nodes in both hierarchies, in depth-first order.
+    let mut pairs: Vec<(Node, Node)> = Vec::new();
+    let mut queue = vec![(left_root, right_root)];
+    while let Some((l, r)) = queue.pop() {
+        if full(&diffs) {
+            return diffs;
+        }
+        pairs.push((l, r));
+        let (l_op, r_op) = (op_label(left.get_optype(l)), op_label(right.get_optype(r)));
+        if l_op != r_op {
+            diffs.push(NodeDifference::Operation {
+                left: l.index(),
+                right: r.index(),
+                left_op: l_op,
+                right_op: r_op,
+            });
+            continue;
+        }
+        if precision == Precision::Exact {
+            if let Some(difference) = exact_difference(left, l, right, r) {
+                diffs.push(difference);
+                continue;
+            }
+        }
+        let (l_count, r_count) = (left.children(l).count(), right.children(r).count());
+        if l_count != r_count {
+            diffs.push(NodeDifference::Children {
+                left: l.index(),
+                right: r.index(),
+                left_count: l_count,
+                right_count: r_count,
+            });
+            continue;
+        }
+        let children = left.children(l).zip(right.children(r)).collect::<Vec<_>>();
+        queue.extend(children.into_iter().rev());
+    }
+
+    // Check that the edges between paired nodes match.
+    //
+    // Sources outside the compared regions, such as called functions or
+    // non-local edges, cannot be paired and are identified by label instead.
+    let pairing: HashMap<Node, Node> = pairs.iter().copied().collect();
+    let right_nodes: HashSet<Node> = pairs.iter().map(|&(_, r)| r).collect();
+    let (left_names, right_names) = (function_names(left), function_names(right));
+    for &(l, r) in &pairs {
+        for port in 0..left.num_inputs(l) {
+            if full(&diffs) {
+                return diffs;
+            }
+            let port = IncomingPort::from(port);
+            let mut l_sources = left
+                .linked_outputs(l, port)
+                .map(|(n, p)| {
+                    let source = match pairing.get(&n) {
+                        Some(&paired) => Source::Paired(paired),
+                        None => Source::External(external_label(left, n, &left_names)),
+                    };
+                    (source, p.index())
+                })
+                .collect::<Vec<_>>();
+            let mut r_sources = right
+                .linked_outputs(r, port)
+                .map(|(n, p)| {
+                    let source = if right_nodes.contains(&n) {
+                        Source::Paired(n)
+                    } else {
+                        Source::External(external_label(right, n, &right_names))
+                    };
+                    (source, p.index())
+                })
+                .collect::<Vec<_>>();
+            l_sources.sort();
+            r_sources.sort();
+            if l_sources != r_sources {
+                diffs.push(NodeDifference::Input {
+                    left: l.index(),
+                    right: r.index(),
+                    port: port.index(),
+                });
+            }
+        }
+    }
+
+    diffs
+}
+
+/// Compare the full operations and metadata of two nodes with the same label.
+fn exact_difference(
+    left: &impl HugrView,
+    l: Node,
+    right: &impl HugrView,
+    r: Node,
+) -> Option<NodeDifference> {
+    let (l_op, r_op) = (left.get_optype(l), right.get_optype(r));
+    if l_op != r_op {
+        return Some(NodeDifference::Parameters {
+            left: l.index(),
+            right: r.index(),
+            op: op_label(l_op),
+        });
+    }
+    let empty = Default::default();
+    let l_meta = left.get_node_metadata(l).unwrap_or(&empty);
+    let r_meta = right.get_node_metadata(r).unwrap_or(&empty);
+    let key = l_meta
+        .keys()
+        .chain(r_meta.keys())
+        .find(|&key| l_meta.get(key) != r_meta.get(key))?;
+    Some(NodeDifference::Metadata {
+        left: l.index(),
+        right: r.index(),
+        key: key.clone(),
+    })
+}
+
+/// The source of an edge, in terms of the nodes of the second HUGR.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Source {
+    /// A node in the compared region.
+    Paired(Node),
+    /// A node outside the compared region, identified by its label.
+    External(String),
+}
+
+/// The qualified names of the functions in a HUGR.
+fn function_names(hugr: &impl HugrView) -> HashMap<Node, String> {
+    functions::functions(hugr)
+        .into_iter()
+        .map(|f| (f.node, f.qualified_name))
+        .collect()
+}
+
+/// A label identifying a node outside the compared regions.
+///
+/// Functions are identified by their qualified name, other nodes by their operation.
+fn external_label(hugr: &impl HugrView, node: Node, names: &HashMap<Node, String>) -> String {
+    match names.get(&node) {
+        Some(name) => format!("function {name}"),
+        None => op_label(hugr.get_optype(node)),
+    }
+}
+
+/// A label identifying the operation of a node, including function names.
+fn op_label(op: &OpType) -> String {
+    match op {
+        OpType::FuncDefn(f) => format!("FuncDefn({})", f.name),
+        OpType::FuncDecl(f) => format!("FuncDecl({})", f.name),
+        op => op.name().to_string(),
+    }
+}
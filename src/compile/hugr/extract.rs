@@ -0,0 +1,37 @@
+//! Extraction of individual functions into their own packages.
+
+use hugr::algorithms::RemoveDeadFuncsPass;
+use hugr::package::Package;
+use hugr::{Hugr, HugrView, Node};
+
+/// Returns a package containing a function and all the functions it
+/// transitively calls, removing every other function from the module.
+///
+/// Nested functions are extracted along with the top-level function they are
+/// defined in.
+pub fn extract_function(hugr: &Hugr, func: Node) -> anyhow::Result<Package> {
+    let root = hugr.root();
+    let mut top_level = func;
+    while let Some(parent) = hugr.get_parent(top_level).filter(|&p| p != root) {
+        top_level = parent;
+    }
+
+    let mut extracted = hugr.clone();
+    RemoveDeadFuncsPass::default()
+        .with_module_entry_points([top_level])
+        .run(&mut extracted)?;
+    Ok(Package::new([extracted])?)
+}
+
+/// Returns a file name for the extracted function, replacing characters that
+/// are not valid in paths.
+pub fn extracted_file_name(qualified_name: &str) -> String {
+    let name: String = qualified_name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '<' | '>' | '"' | '|' | '?' | '*' => '_',
+            c => c,
+        })
+        .collect();
+    format!("{name}.hugr")
+}
@@ -0,0 +1,482 @@
+//! Traversal of the function definitions and declarations in a HUGR.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+use derive_more::{Display, Error};
+use hugr::extension::prelude::{bool_t, qb_t};
+use hugr::std_extensions::arithmetic::{float_types, int_types};
+use hugr::types::{PolyFuncType, Type, TypeArg, TypeEnum};
+use hugr::{HugrView, Node, NodeIndex, ops};
+use tket2_hseries::extension::futures;
+
+/// Metadata key holding the name of a HUGR module.
+const MODULE_NAME_METADATA: &str = "name";
+
+/// A function definition or declaration in a HUGR.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionInfo {
+    /// The `FuncDefn` or `FuncDecl` node.
+    pub node: Node,
+    /// The function name.
+    pub name: String,
+    /// The function name prefixed by the names of the functions it is nested in,
+    /// separated by dots.
+    pub qualified_name: String,
+    /// The function signature.
+    pub signature: PolyFuncType,
+    /// Whether the function is a declaration without a body.
+    pub is_declaration: bool,
+}
+
+/// Returns all the function definitions and declarations in the HUGR,
+/// including those nested inside other functions, in hierarchy order.
+pub fn functions(hugr: &impl HugrView) -> Vec<FunctionInfo> {
+    let mut functions = Vec::new();
+    let mut queue = vec![(hugr.root(), String::new())];
+
+    while let Some((parent, prefix)) = queue.pop() {
+        for node in hugr.children(parent) {
+            let (name, signature, is_declaration) = match hugr.get_optype(node) {
+                ops::OpType::FuncDefn(ops::FuncDefn { name, signature }) => {
+                    (name, signature, false)
+                }
+                ops::OpType::FuncDecl(ops::FuncDecl { name, signature }) => (name, signature, true),
+                _ => {
+                    // Functions may be nested in any container inside a function body.
+                    if hugr.children(node).next().is_some() {
+                        queue.push((node, prefix.clone()));
+                    }
+                    continue;
+                }
+            };
+            let qualified_name = format!("{prefix}{name}");
+            queue.push((node, format!("{qualified_name}.")));
+            functions.push(FunctionInfo {
+                node,
+                name: name.clone(),
+                qualified_name,
+                signature: signature.clone(),
+                is_declaration,
+            });
+        }
+    }
+
+    functions.sort_by_key(|f| f.node.index());
+    functions
+}
+
+/// Returns the function definition or declaration a node belongs to, if any.
+///
+/// For function nodes this is the function itself.
+pub fn enclosing_function(hugr: &impl HugrView, node: Node) -> Option<Node> {
+    let mut current = Some(node);
+    while let Some(n) = current {
+        if matches!(
+            hugr.get_optype(n),
+            ops::OpType::FuncDefn(_) | ops::OpType::FuncDecl(_)
+        ) {
+            return Some(n);
+        }
+        current = hugr.get_parent(n);
+    }
+    None
+}
+
+/// Returns the functions each function refers to, through calls or function loads.
+///
+/// Every function in the HUGR has an entry, possibly empty.
+pub fn call_graph(hugr: &impl HugrView) -> BTreeMap<Node, BTreeSet<Node>> {
+    let mut graph: BTreeMap<Node, BTreeSet<Node>> = functions(hugr)
+        .into_iter()
+        .map(|f| (f.node, BTreeSet::new()))
+        .collect();
+    for node in hugr.nodes() {
+        if !matches!(
+            hugr.get_optype(node),
+            ops::OpType::Call(_) | ops::OpType::LoadFunction(_)
+        ) {
+            continue;
+        }
+        let (Some(caller), Some(callee)) = (
+            hugr.get_parent(node)
+                .and_then(|p| enclosing_function(hugr, p)),
+            hugr.static_source(node),
+        ) else {
+            continue;
+        };
+        graph.entry(caller).or_default().insert(callee);
+    }
+    graph
+}
+
+/// Returns the functions reachable from `root` in the call graph, including itself.
+pub fn reachable(graph: &BTreeMap<Node, BTreeSet<Node>>, root: Node) -> BTreeSet<Node> {
+    let mut visited = BTreeSet::new();
+    let mut queue = vec![root];
+    while let Some(node) = queue.pop() {
+        if visited.insert(node) {
+            queue.extend(graph.get(&node).into_iter().flatten().copied());
+        }
+    }
+    visited
+}
+
+/// Returns the name of the HUGR module, if it has one.
+pub fn module_name(hugr: &impl HugrView) -> Option<&str> {
+    hugr.get_metadata(hugr.root(), MODULE_NAME_METADATA)?
+        .as_str()
+}
+
+/// A reference to a function in a HUGR, as given on the command line.
+///
+/// Can be a plain function name, a qualified name with the enclosing module or
+/// functions separated by dots (`module.outer.inner`), a node id (`@n42`), a name
+/// and node id (`main@n42`), or a name and signature (`main: [] -> [bool]`).
+/// See [SignatureSpec] for the signature format.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FunctionSpec {
+    /// The, possibly qualified, function name.
+    pub name: Option<String>,
+    /// The index of the function node.
+    pub node: Option<usize>,
+    /// The function signature.
+    pub signature: Option<SignatureSpec>,
+}
+
+impl FromStr for FunctionSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some((name, signature)) = s.split_once(':') {
+            let signature = signature
+                .parse()
+                .map_err(|e| format!("Invalid signature in function reference '{s}'. {e}"))?;
+            return Ok(FunctionSpec {
+                name: Some(name.trim().to_string()).filter(|n| !n.is_empty()),
+                node: None,
+                signature: Some(signature),
+            });
+        }
+        if let Some((name, node)) = s.split_once('@') {
+            let index = node.strip_prefix('n').unwrap_or(node);
+            let index = index
+                .parse()
+                .map_err(|_| format!("Invalid node id '@{node}' in function reference '{s}'"))?;
+            return Ok(FunctionSpec {
+                name: Some(name.to_string()).filter(|n| !n.is_empty()),
+                node: Some(index),
+                signature: None,
+            });
+        }
+        Ok(FunctionSpec {
+            name: Some(s.to_string()),
+            ..Default::default()
+        })
+    }
+}
+
+impl std::fmt::Display for FunctionSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(name) = &self.name {
+            write!(f, "{name}")?;
+        }
+        if let Some(node) = self.node {
+            write!(f, "@n{node}")?;
+        }
+        if let Some(signature) = &self.signature {
+            write!(f, ": {signature}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FunctionSpec {
+    /// Returns `true` if the function matches this reference.
+    ///
+    /// Unqualified names match functions at any nesting level. `module` is the
+    /// name of the HUGR module containing the function, which may prefix
+    /// qualified names.
+    pub fn matches(&self, func: &FunctionInfo, module: Option<&str>) -> bool {
+        let name_matches = self
+            .name
+            .as_ref()
+            .is_none_or(|name| *name == func.name || self.matches_qualified_name(func, module));
+        let node_matches = self.node.is_none_or(|node| node == func.node.index());
+        let signature_matches = self
+            .signature
+            .as_ref()
+            .is_none_or(|signature| *signature == SignatureSpec::of(&func.signature));
+        name_matches && node_matches && signature_matches
+    }
+
+    /// Returns the functions matching this reference.
+    ///
+    /// Functions whose qualified name is the requested name take precedence, so
+    /// that `main` selects the top-level `main` function rather than also
+    /// matching the functions named `main` nested in other functions.
+    pub fn select<'a>(
+        &self,
+        functions: &'a [FunctionInfo],
+        module: Option<&str>,
+    ) -> Vec<&'a FunctionInfo> {
+        let matching = functions
+            .iter()
+            .filter(|f| self.matches(f, module))
+            .collect::<Vec<_>>();
+        let qualified = matching
+            .iter()
+            .copied()
+            .filter(|f| self.matches_qualified_name(f, module))
+            .collect::<Vec<_>>();
+        if qualified.is_empty() {
+            matching
+        } else {
+            qualified
+        }
+    }
+
+    /// Returns `true` if the requested name is the qualified name of the
+    /// function, optionally prefixed by the module name.
+    fn matches_qualified_name(&self, func: &FunctionInfo, module: Option<&str>) -> bool {
+        self.name.as_ref().is_some_and(|name| {
+            *name == func.qualified_name
+                || module.is_some_and(|m| *name == format!("{m}.{}", func.qualified_name))
+        })
+    }
+}
+
+/// The signature of a function, as given in a [FunctionSpec].
+///
+/// Written as `[inputs] -> [outputs]`, with comma-separated types, e.g.
+/// `[int, qubit] -> [bool]`. Booleans, 64-bit integers, 64-bit floats and
+/// qubits are written `bool`, `int`, `float` and `qubit`, and other types as
+/// printed by HUGR. Types are compared ignoring case and whitespace. The
+/// type parameters of polymorphic functions are not part of the signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureSpec {
+    /// The normalised names of the input types.
+    pub inputs: Vec<String>,
+    /// The normalised names of the output types.
+    pub outputs: Vec<String>,
+}
+
+impl SignatureSpec {
+    /// The signature of a function, ignoring its type parameters.
+    pub fn of(signature: &PolyFuncType) -> Self {
+        let names = |types: &[Type]| types.iter().map(|ty| normalise(&type_name(ty))).collect();
+        let body = signature.body();
+        Self {
+            inputs: names(body.input_types()),
+            outputs: names(body.output_types()),
+        }
+    }
+}
+
+impl FromStr for SignatureSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [inputs, outputs] = split_top_level(s, "->")[..] else {
+            return Err(format!(
+                "Expected `[inputs] -> [outputs]`, got '{}'",
+                s.trim()
+            ));
+        };
+        let types = |row: &str| -> Result<Vec<String>, String> {
+            let row = row.trim();
+            let Some(inner) = row.strip_prefix('[').and_then(|r| r.strip_suffix(']')) else {
+                return Err(format!("Expected a bracketed list of types, got '{row}'"));
+            };
+            Ok(split_top_level(inner, ",")
+                .into_iter()
+                .map(normalise)
+                .filter(|ty| !ty.is_empty())
+                .collect())
+        };
+        Ok(Self {
+            inputs: types(inputs)?,
+            outputs: types(outputs)?,
+        })
+    }
+}
+
+impl std::fmt::Display for SignatureSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{}] -> [{}]",
+            self.inputs.join(", "),
+            self.outputs.join(", ")
+        )
+    }
+}
+
+impl FunctionInfo {
+    /// Check that the function can be used as a program entrypoint.
+    ///
+    /// Entrypoints must be monomorphic, take only classical scalar inputs, and
+    /// return classical scalars or measurement results.
+    pub fn check_entrypoint_signature(&self) -> Result<(), EntrypointSignatureError> {
+        if !self.signature.params().is_empty() {
+            return Err(EntrypointSignatureError::Polymorphic {
+                n_params: self.signature.params().len(),
+            });
+        }
+        let body = self.signature.body();
+        if let Some((index, ty)) = body
+            .input_types()
+            .iter()
+            .enumerate()
+            .find(|(_, ty)| !is_classical_scalar(ty))
+        {
+            return Err(EntrypointSignatureError::UnsupportedInput {
+                index,
+                ty: ty.clone(),
+            });
+        }
+        if let Some((index, ty)) = body
+            .output_types()
+            .iter()
+            .enumerate()
+            .find(|(_, ty)| !is_classical_scalar(ty) && !is_measurement(ty))
+        {
+            return Err(EntrypointSignatureError::UnsupportedOutput {
+                index,
+                ty: ty.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Returns `true` if the type is a boolean, integer or float.
+fn is_classical_scalar(ty: &Type) -> bool {
+    if *ty == bool_t() || *ty == float_types::float64_type() {
+        return true;
+    }
+    match ty.as_type_enum() {
+        TypeEnum::Extension(custom) => {
+            *custom.parent_extension() == int_types::EXTENSION_ID
+                && *custom.name() == int_types::INT_TYPE_ID
+        }
+        _ => false,
+    }
+}
+
+/// Returns `true` if the type is a (possibly future) measurement result.
+fn is_measurement(ty: &Type) -> bool {
+    match ty.as_type_enum() {
+        TypeEnum::Extension(custom) => {
+            *custom.parent_extension() == futures::EXTENSION_ID
+                && *custom.name() == *futures::FUTURE_TYPE_NAME
+                && custom.args() == [bool_t().into()]
+        }
+        _ => false,
+    }
+}
+
+/// Reason why a function cannot be used as an entrypoint.
+#[derive(Debug, Display, Error, Clone, PartialEq)]
+pub enum EntrypointSignatureError {
+    /// The function has type parameters.
+    #[display("it is polymorphic over {n_params} type parameters")]
+    Polymorphic {
+        /// The number of type parameters.
+        n_params: usize,
+    },
+    /// The function takes an input that cannot be provided by the caller.
+    #[display(
+        "input {index} has type {ty}, but entrypoints may only take bool, int or float inputs"
+    )]
+    UnsupportedInput {
+        /// The input index.
+        index: usize,
+        /// The input type.
+        ty: Type,
+    },
+    /// The function returns a value that cannot be passed back to the caller.
+    #[display(
+        "output {index} has type {ty}, but entrypoints may only return bool, int, float or measurement results"
+    )]
+    UnsupportedOutput {
+        /// The output index.
+        index: usize,
+        /// The output type.
+        ty: Type,
+    },
+}
+
+/// Returns the name in `candidates` closest to `name`, if any is close enough to
+/// be a plausible typo.
+pub fn closest_name<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    candidates
+        .into_iter()
+        .map(|candidate| (strsim::levenshtein(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= candidate.len().max(name.len()) / 3 + 1)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+/// The name of a type in a [SignatureSpec].
+fn type_name(ty: &Type) -> String {
+    if *ty == bool_t() {
+        return "bool".to_string();
+    }
+    if *ty == float_types::float64_type() {
+        return "float".to_string();
+    }
+    if *ty == qb_t() {
+        return "qubit".to_string();
+    }
+    match ty.as_type_enum() {
+        TypeEnum::Extension(custom)
+            if *custom.parent_extension() == int_types::EXTENSION_ID
+                && *custom.name() == int_types::INT_TYPE_ID
+                && custom.args() == [TypeArg::BoundedNat { n: 6 }] =>
+        {
+            "int".to_string()
+        }
+        _ => ty.to_string(),
+    }
+}
+
+/// Normalise a type name for comparisons, ignoring case and whitespace.
+fn normalise(name: impl AsRef<str>) -> String {
+    name.as_ref()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Split a string at the occurrences of `separator` outside of any brackets.
+fn split_top_level<'a>(s: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '[' | '(' | '<' => depth += 1,
+            ']' | ')' => depth = depth.saturating_sub(1),
+            // Not the end of a bracket if it is part of an arrow.
+            '>' if !s[..i].ends_with('-') => depth = depth.saturating_sub(1),
+            _ if depth == 0 && s[i..].starts_with(separator) => {
+                parts.push(&s[start..i]);
+                start = i + separator.len();
+                // Skip the rest of the separator.
+                for _ in 1..separator.chars().count() {
+                    chars.next();
+                }
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
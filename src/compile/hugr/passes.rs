@@ -0,0 +1,207 @@
+//! Named HUGR passes run before lowering to LLVM.
+
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+use hugr::algorithms::const_fold::ConstantFoldPass;
+use hugr::algorithms::merge_bbs::merge_basic_blocks;
+use hugr::algorithms::{MonomorphizePass, RemoveDeadFuncsPass};
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::hugr::rewrite::inline_call::InlineCall;
+use hugr::hugr::rewrite::inline_dfg::InlineDFG;
+use hugr::hugr::views::SiblingMut;
+use hugr::ops::OpType;
+use hugr::ops::handle::CfgID;
+use hugr::{Hugr, HugrView, Node};
+
+use crate::cli::OptimisationLevel;
+
+/// Extensions whose operations have no side effects, and can be removed when
+/// their outputs are unused.
+const PURE_EXTENSIONS: &[&str] = &[
+    "arithmetic.int",
+    "arithmetic.float",
+    "arithmetic.conversions",
+    "logic",
+];
+
+/// Prefixes of the operations in `arithmetic.int` that may trap on a zero
+/// divisor, and must be kept even if their outputs are unused.
+const TRAPPING_INT_OPS: &[&str] = &["idiv", "imod"];
+
+/// A pass over the HUGR.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display, strum::EnumIter)]
+#[strum(serialize_all = "kebab-case")]
+pub enum HugrPass {
+    /// Instantiate polymorphic functions at each of their uses.
+    Monomorphize,
+    /// Remove functions that are not reachable from the entrypoint.
+    DeadFuncs,
+    /// Inline calls to functions that do not call other functions.
+    Inline,
+    /// Inline nested DFG nodes into their parent region.
+    NormalizeDfgs,
+    /// Merge sequential basic blocks in CFGs.
+    NormalizeCfgs,
+    /// Evaluate operations on constant inputs.
+    ConstFold,
+    /// Remove side-effect-free operations whose outputs are unused.
+    DeadCode,
+}
+
+impl HugrPass {
+    /// The passes run at the given optimisation level, in order.
+    pub fn pipeline(opt: OptimisationLevel) -> Vec<HugrPass> {
+        let mut passes = vec![HugrPass::Monomorphize, HugrPass::DeadFuncs];
+        if opt >= OptimisationLevel::O3 {
+            passes.push(HugrPass::Inline);
+        }
+        if opt >= OptimisationLevel::O1 {
+            passes.extend([HugrPass::NormalizeDfgs, HugrPass::NormalizeCfgs]);
+        }
+        if opt >= OptimisationLevel::O2 {
+            passes.extend([HugrPass::ConstFold, HugrPass::DeadCode]);
+        }
+        passes
+    }
+
+    /// Run the pass on a HUGR.
+    ///
+    /// The number of nodes that were added, removed, replaced or moved is
+    /// logged at debug level.
+    pub fn run(&self, hugr: &mut Hugr, entrypoint: Option<Node>) -> anyhow::Result<()> {
+        // Snapshots clone every operation, so only take them when they are logged.
+        let before = log::log_enabled!(log::Level::Debug).then(|| snapshot(hugr));
+        match self {
+            HugrPass::Monomorphize => MonomorphizePass::default().run(hugr)?,
+            HugrPass::DeadFuncs => match entrypoint {
+                Some(entrypoint) => RemoveDeadFuncsPass::default()
+                    .with_module_entry_points([entrypoint])
+                    .run(hugr)?,
+                None => log::debug!("Skipping pass {self}, no entrypoint was given"),
+            },
+            HugrPass::Inline => inline_leaf_calls(hugr)?,
+            HugrPass::NormalizeDfgs => inline_dfgs(hugr)?,
+            HugrPass::NormalizeCfgs => merge_cfg_blocks(hugr)?,
+            HugrPass::ConstFold => ConstantFoldPass::default().run(hugr)?,
+            HugrPass::DeadCode => remove_dead_code(hugr),
+        }
+        if let Some(before) = before {
+            let changed = changed_nodes(&before, &snapshot(hugr));
+            log::debug!("Pass {self} changed {changed} nodes");
+        }
+        Ok(())
+    }
+}
+
+/// The operation and parent of every node in a HUGR.
+type Snapshot = HashMap<Node, (OpType, Option<Node>)>;
+
+/// Take a [Snapshot] of a HUGR.
+fn snapshot(hugr: &Hugr) -> Snapshot {
+    hugr.nodes()
+        .map(|n| (n, (hugr.get_optype(n).clone(), hugr.get_parent(n))))
+        .collect()
+}
+
+/// Count the nodes that differ between two snapshots of a HUGR.
+///
+/// A node is changed if it was added or removed, or if its operation or
+/// parent differs. Node indices may be reused by a rewrite, in which case
+/// the replaced node is only counted once.
+fn changed_nodes(before: &Snapshot, after: &Snapshot) -> usize {
+    let removed = before.keys().filter(|n| !after.contains_key(n)).count();
+    let added_or_modified = after
+        .iter()
+        .filter(|(n, node)| before.get(n) != Some(node))
+        .count();
+    removed + added_or_modified
+}
+
+/// Repeatedly inline calls to functions that contain no calls themselves,
+/// until no such calls remain.
+fn inline_leaf_calls(hugr: &mut Hugr) -> anyhow::Result<()> {
+    loop {
+        let leaf_calls = hugr
+            .nodes()
+            .filter(|&n| matches!(hugr.get_optype(n), OpType::Call(_)))
+            .filter(|&call| {
+                hugr.static_source(call).is_some_and(|func| {
+                    matches!(hugr.get_optype(func), OpType::FuncDefn(_))
+                        && !hugr
+                            .descendants(func)
+                            .any(|n| matches!(hugr.get_optype(n), OpType::Call(_)))
+                })
+            })
+            .collect::<Vec<_>>();
+        if leaf_calls.is_empty() {
+            return Ok(());
+        }
+        for call in leaf_calls {
+            hugr.apply_rewrite(InlineCall::new(call))?;
+        }
+    }
+}
+
+/// Inline every DFG node nested in another dataflow region.
+fn inline_dfgs(hugr: &mut Hugr) -> anyhow::Result<()> {
+    let dfgs = hugr
+        .nodes()
+        .filter(|&n| matches!(hugr.get_optype(n), OpType::DFG(_)) && n != hugr.root())
+        .collect::<Vec<_>>();
+    for dfg in dfgs {
+        hugr.apply_rewrite(InlineDFG(dfg.into()))?;
+    }
+    Ok(())
+}
+
+/// Merge basic blocks with a single successor into that successor, in every CFG.
+fn merge_cfg_blocks(hugr: &mut Hugr) -> anyhow::Result<()> {
+    let cfgs = hugr
+        .nodes()
+        .filter(|&n| matches!(hugr.get_optype(n), OpType::CFG(_)))
+        .collect::<Vec<_>>();
+    for cfg in cfgs {
+        let mut sibling = SiblingMut::<CfgID>::try_new(hugr, cfg)?;
+        merge_basic_blocks(&mut sibling);
+    }
+    Ok(())
+}
+
+/// Remove pure operations without any consumers, until a fixpoint is reached.
+fn remove_dead_code(hugr: &mut Hugr) {
+    loop {
+        let dead = hugr
+            .nodes()
+            .filter(|&n| is_pure(hugr.get_optype(n)))
+            .filter(|&n| hugr.all_linked_inputs(n).next().is_none())
+            .collect::<Vec<_>>();
+        if dead.is_empty() {
+            return;
+        }
+        for node in dead {
+            hugr.remove_node(node);
+        }
+    }
+}
+
+/// Returns `true` if the operation can be removed without changing the
+/// semantics of the program, provided its outputs are unused.
+fn is_pure(op: &OpType) -> bool {
+    match op {
+        OpType::Const(_)
+        | OpType::LoadConstant(_)
+        | OpType::MakeTuple(_)
+        | OpType::UnpackTuple(_)
+        | OpType::Tag(_)
+        | OpType::Noop(_) => true,
+        OpType::ExtensionOp(ext_op) => {
+            let extension = ext_op.def().extension_id().to_string();
+            let name = ext_op.def().name();
+            PURE_EXTENSIONS.contains(&extension.as_str())
+                && !(extension == "arithmetic.int"
+                    && TRAPPING_INT_OPS.iter().any(|op| name.starts_with(op)))
+        }
+        _ => false,
+    }
+}
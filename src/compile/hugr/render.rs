@@ -0,0 +1,238 @@
+//! Graphviz and HTML renderings of a HUGR.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use hugr::ops::{NamedOp, OpType};
+use hugr::types::EdgeKind;
+use hugr::{Direction, HugrView, Node, NodeIndex, Port, PortIndex};
+use itertools::Itertools;
+
+/// Options controlling which part of a HUGR is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// The node whose descendants are rendered.
+    pub root: Node,
+    /// Maximum nesting depth below the root. Deeper containers are collapsed.
+    pub max_depth: Option<usize>,
+}
+
+/// Returns a Graphviz DOT representation of the HUGR, with a cluster for each
+/// container node.
+pub fn dot_string(hugr: &impl HugrView, options: &RenderOptions) -> String {
+    let mut dot = String::new();
+    writeln!(dot, "digraph hugr {{").unwrap();
+    writeln!(dot, "  compound=true;").unwrap();
+    writeln!(dot, "  node [shape=box, fontname=\"monospace\"];").unwrap();
+    write_dot_node(hugr, options.root, 0, options, &mut dot);
+
+    // Edges to or from the inside of a collapsed container are attached to
+    // the container instead, without the inner port labels.
+    let rendered = rendered_nodes(hugr, options);
+    let mut edges = Vec::new();
+    for node in hugr.descendants(options.root) {
+        let source = rendered[&node];
+        for (port, target, target_port) in out_edges(hugr, node) {
+            let Some(&drawn_target) = rendered.get(&target) else {
+                continue;
+            };
+            let redirected = source != node || drawn_target != target;
+            if redirected && source == drawn_target {
+                // The edge is hidden inside a collapsed container.
+                continue;
+            }
+            let mut style = match hugr.get_optype(node).port_kind(port) {
+                Some(EdgeKind::Value(ty)) => format!("label=\"{}\"", escape_dot(&ty.to_string())),
+                Some(EdgeKind::StateOrder) => "style=dotted".to_string(),
+                Some(EdgeKind::ControlFlow) => "style=bold".to_string(),
+                _ => "style=dashed".to_string(),
+            };
+            if source == node {
+                write!(style, ", taillabel=\"{}\"", port.index()).unwrap();
+            }
+            if drawn_target == target {
+                write!(style, ", headlabel=\"{}\"", target_port.index()).unwrap();
+            }
+            edges.push(format!(
+                "  n{} -> n{} [{style}];",
+                source.index(),
+                drawn_target.index()
+            ));
+        }
+    }
+    for edge in edges.into_iter().unique() {
+        writeln!(dot, "{edge}").unwrap();
+    }
+
+    writeln!(dot, "}}").unwrap();
+    dot
+}
+
+fn write_dot_node(
+    hugr: &impl HugrView,
+    node: Node,
+    depth: usize,
+    options: &RenderOptions,
+    dot: &mut String,
+) {
+    let indent = "  ".repeat(depth + 1);
+    let label = escape_dot(&node_label(hugr, node));
+    let expanded = is_expanded(hugr, node, depth, options);
+
+    if !expanded {
+        let collapsed = if hugr.children(node).next().is_some() {
+            " (collapsed)"
+        } else {
+            ""
+        };
+        writeln!(
+            dot,
+            "{indent}n{} [label=\"{label}{collapsed}\"];",
+            node.index()
+        )
+        .unwrap();
+        return;
+    }
+
+    // Containers are drawn as a cluster, with a header node to attach the edges to.
+    writeln!(dot, "{indent}subgraph cluster_{} {{", node.index()).unwrap();
+    writeln!(dot, "{indent}  label=\"{label}\";").unwrap();
+    writeln!(
+        dot,
+        "{indent}  n{} [label=\"{label}\", shape=plaintext];",
+        node.index()
+    )
+    .unwrap();
+    for child in hugr.children(node) {
+        write_dot_node(hugr, child, depth + 1, options, dot);
+    }
+    writeln!(dot, "{indent}}}").unwrap();
+}
+
+/// Returns a self-contained HTML page showing the HUGR hierarchy, where each
+/// region can be collapsed and expanded, along with the node metadata.
+pub fn html_string(hugr: &impl HugrView, options: &RenderOptions) -> String {
+    let mut body = String::new();
+    write_html_node(hugr, options.root, 0, options, &mut body);
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>HUGR</title>
+<style>
+body {{ font-family: monospace; }}
+details {{ margin-left: 1.5em; border-left: 1px solid #ccc; padding-left: 0.5em; }}
+.leaf {{ margin-left: 1.5em; padding-left: 0.5em; }}
+.node-id {{ color: #888; }}
+.edges {{ color: #555; margin-left: 1.5em; }}
+pre {{ background: #f4f4f4; margin: 0.2em 0 0.2em 1.5em; padding: 0.3em; }}
+</style>
+</head>
+<body>
+{body}</body>
+</html>
+"#
+    )
+}
+
+fn write_html_node(
+    hugr: &impl HugrView,
+    node: Node,
+    depth: usize,
+    options: &RenderOptions,
+    html: &mut String,
+) {
+    let label = format!(
+        "{} <span class=\"node-id\">n{}</span>",
+        escape_html(&node_label(hugr, node)),
+        node.index()
+    );
+    let has_children = hugr.children(node).next().is_some();
+
+    if has_children {
+        let open = if is_expanded(hugr, node, depth, options) {
+            " open"
+        } else {
+            ""
+        };
+        writeln!(html, "<details{open}><summary>{label}</summary>").unwrap();
+    } else {
+        writeln!(html, "<div class=\"leaf\">{label}").unwrap();
+    }
+
+    let inputs = hugr
+        .all_linked_outputs(node)
+        .map(|(source, port)| format!("n{}:{}", source.index(), port.index()))
+        .join(", ");
+    if !inputs.is_empty() {
+        writeln!(html, "<div class=\"edges\">inputs from {inputs}</div>").unwrap();
+    }
+    if let Some(metadata) = hugr.get_node_metadata(node).filter(|m| !m.is_empty()) {
+        let metadata = serde_json::to_string_pretty(metadata).unwrap_or_default();
+        writeln!(html, "<pre>{}</pre>", escape_html(&metadata)).unwrap();
+    }
+
+    if has_children {
+        for child in hugr.children(node) {
+            write_html_node(hugr, child, depth + 1, options, html);
+        }
+        writeln!(html, "</details>").unwrap();
+    } else {
+        writeln!(html, "</div>").unwrap();
+    }
+}
+
+/// Returns `true` if the children of a container node should be rendered.
+fn is_expanded(hugr: &impl HugrView, node: Node, depth: usize, options: &RenderOptions) -> bool {
+    hugr.children(node).next().is_some() && options.max_depth.is_none_or(|max| depth < max)
+}
+
+/// Maps every node in the rendered part of the HUGR to the node drawing it,
+/// either as a box or as a cluster header.
+///
+/// Nodes inside a collapsed container are drawn by the container.
+fn rendered_nodes(hugr: &impl HugrView, options: &RenderOptions) -> HashMap<Node, Node> {
+    let mut nodes = HashMap::new();
+    let mut queue = vec![(options.root, 0)];
+    while let Some((node, depth)) = queue.pop() {
+        nodes.insert(node, node);
+        if is_expanded(hugr, node, depth, options) {
+            queue.extend(hugr.children(node).map(|child| (child, depth + 1)));
+        } else {
+            nodes.extend(hugr.descendants(node).map(|inner| (inner, node)));
+        }
+    }
+    nodes
+}
+
+/// Returns the outgoing edges of a node, with the source port and target node and port.
+fn out_edges(hugr: &impl HugrView, node: Node) -> Vec<(Port, Node, Port)> {
+    hugr.node_ports(node, Direction::Outgoing)
+        .flat_map(|port| {
+            hugr.linked_ports(node, port)
+                .map(move |(target, target_port)| (port, target, target_port))
+        })
+        .collect()
+}
+
+/// A short description of a node.
+fn node_label(hugr: &impl HugrView, node: Node) -> String {
+    match hugr.get_optype(node) {
+        OpType::FuncDefn(f) => format!("FuncDefn: {}", f.name),
+        OpType::FuncDecl(f) => format!("FuncDecl: {}", f.name),
+        op => op.name().to_string(),
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
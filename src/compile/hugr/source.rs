@@ -0,0 +1,46 @@
+//! Source locations attached to HUGR nodes.
+
+use std::fmt;
+
+use hugr::{HugrView, Node};
+use serde::{Deserialize, Serialize};
+
+/// Metadata key holding the source location of a node.
+pub const SOURCE_LOCATION_METADATA: &str = "guppyc.source";
+
+/// A location in a source file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceLocation {
+    /// The source file path.
+    pub file: String,
+    /// The line number, starting at 1.
+    pub line: u32,
+    /// The column number, starting at 1.
+    #[serde(default)]
+    pub column: Option<u32>,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{column}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the source location of a node, or of its closest ancestor with one.
+pub fn source_location(hugr: &impl HugrView, node: Node) -> Option<SourceLocation> {
+    let mut current = Some(node);
+    while let Some(n) = current {
+        if let Some(location) = hugr
+            .get_metadata(n, SOURCE_LOCATION_METADATA)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+        {
+            return Some(location);
+        }
+        current = hugr.get_parent(n);
+    }
+    None
+}
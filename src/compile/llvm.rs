@@ -1,20 +1,35 @@
 //! LLVM IR stage.
 
-use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::{fs, io};
 
 use hugr::llvm::CodegenExtsBuilder;
 use hugr::llvm::custom::CodegenExtsMap;
+use hugr::llvm::emit::Namer;
 use hugr::llvm::inkwell::context::Context;
 use hugr::llvm::inkwell::memory_buffer::MemoryBuffer;
-use hugr::llvm::inkwell::module::Module;
+use hugr::llvm::inkwell::module::{Linkage, Module};
 use hugr::llvm::inkwell::passes::PassManager;
+use hugr::llvm::inkwell::targets::{FileType, TargetMachine};
 use hugr::llvm::utils::fat::FatExt;
-use hugr::{Hugr, Node};
+use hugr::{Hugr, HugrView, Node};
 
-use crate::cli::{CliArgs, OptimisationLevel};
+use crate::cli::{CliArgs, InputKind, OptimisationLevel};
 
 use super::{CompilationStage, GenericStage, Stage};
 
+pub mod debug;
+pub mod link;
+pub mod manifest;
+pub mod qir;
+pub mod quantum;
+pub mod target;
+
+use debug::DebugInfo;
+use manifest::Manifest;
+use quantum::Tket2CodegenExtension;
+
 /// An llvm IR object.
 #[derive(Debug)]
 pub struct LLVMStage {
@@ -25,8 +40,14 @@ pub struct LLVMStage {
     /// We have to pre-compute this because `inkwell` is quite restrictive with
     /// `Module` lifetimes and buffer ownership -.-
     pub module_text: Option<String>,
-    /// Mangled name of the entrypoint function.
+    /// Object code for the selected target, if requested.
+    pub object: Option<MemoryBuffer>,
+    /// Assembly for the selected target, if requested.
+    pub assembly: Option<MemoryBuffer>,
+    /// Name of the entrypoint function in the module.
     pub entrypoint: Option<String>,
+    /// The symbols in the module.
+    pub manifest: Manifest,
 }
 
 impl CompilationStage for LLVMStage {
@@ -53,6 +74,25 @@ impl CompilationStage for LLVMStage {
             fs::write(bitcode, self.module_bitcode.as_slice())?;
         }
 
+        if let Some(object_out) = &out.object {
+            match &self.object {
+                Some(object) => fs::write(object_out, object.as_slice())?,
+                None => log::warn!("Skipping the object output for an invalid module"),
+            }
+        }
+
+        if let Some(asm_out) = &out.asm {
+            match &self.assembly {
+                Some(assembly) => fs::write(asm_out, assembly.as_slice())?,
+                None => log::warn!("Skipping the assembly output for an invalid module"),
+            }
+        }
+
+        if let Some(manifest_out) = &out.manifest {
+            let writer = io::BufWriter::new(fs::File::create(manifest_out)?);
+            serde_json::to_writer_pretty(writer, &self.manifest)?;
+        }
+
         Ok(())
     }
 }
@@ -62,40 +102,216 @@ impl LLVMStage {
     ///
     /// Assumes any hugr-side rewrites have already been done.
     pub fn from_hugr(hugr: Hugr, entrypoint: Option<Node>, args: &CliArgs) -> anyhow::Result<Self> {
-        let namer = hugr::llvm::emit::Namer::default();
-        let mangled_name = entrypoint
-            .map(|entrypoint| namer.name_func(args.entrypoint.as_ref().unwrap(), entrypoint));
+        let not_a_function = |&node: &Node| hugr.get_optype(node).as_func_defn().is_none();
+        if let Some(entrypoint) = entrypoint.filter(not_a_function) {
+            anyhow::bail!("Entrypoint {entrypoint} is not a function definition");
+        }
 
+        let namer = Namer::default();
+        let manifest = Manifest::from_hugr(&hugr, &namer, entrypoint);
         let context = Context::create();
-        let module = compile_module(&hugr, &context, namer)?;
-        optimise_module(&module, args)?;
+        let module = compile_module(&hugr, &context, namer, &manifest, args)?;
+        Self::from_module(&context, &module, manifest, None, args)
+    }
+
+    /// Load an existing LLVM module, to be optimised and re-targeted.
+    ///
+    /// The module keeps its target triple and data layout unless a target,
+    /// CPU or features are selected. Modules without a target triple are
+    /// compiled for the host.
+    ///
+    /// The entrypoint, if any, is the LLVM function named by `--entrypoint`.
+    pub fn from_file(path: &Path, kind: InputKind, args: &CliArgs) -> anyhow::Result<Self> {
+        let context = Context::create();
+        let module = match kind {
+            InputKind::LlvmBitcode => parse_bitcode_file(path, &context)?,
+            _ => parse_ir_file(path, &context)?,
+        };
+        let manifest = Manifest {
+            entrypoint: args.entrypoint.clone(),
+            ..Default::default()
+        };
+        let machine = if args.target.is_default() {
+            target::module_target_machine(&module, args.opt)?
+        } else {
+            None
+        };
+        Self::from_module(&context, &module, manifest, machine, args)
+    }
+
+    /// Link, verify and optimise a module for the selected target, and
+    /// pre-compute the requested artifacts.
+    ///
+    /// If a `module_target` machine is given, it must match the target the
+    /// module already declares, which is kept.
+    fn from_module<'c>(
+        context: &'c Context,
+        module: &Module<'c>,
+        mut manifest: Manifest,
+        module_target: Option<TargetMachine>,
+        args: &CliArgs,
+    ) -> anyhow::Result<Self> {
+        link::link_libraries(context, module, &args.link)?;
+        let machine = match module_target {
+            Some(machine) => machine,
+            None => {
+                let machine = args.target.target_machine(args.opt)?;
+                target::set_target(module, &machine);
+                machine
+            }
+        };
+        let mut valid = verified(verify_module(module, "before optimisation"), args)?;
+        if valid {
+            optimise_module(module, args)?;
+            valid = verified(verify_module(module, "after optimisation"), args)?;
+        }
+        if let Some(profile) = args.target.qir_profile.filter(|_| valid) {
+            let entrypoint = manifest.entrypoint.as_deref().ok_or_else(|| {
+                anyhow::anyhow!("The {profile} QIR profile requires an entrypoint")
+            })?;
+            profile.apply(module, entrypoint)?;
+            valid = verified(
+                verify_module(module, "after applying the QIR profile"),
+                args,
+            )?;
+        }
+
+        if let Some(export_name) = &args.output.export_name {
+            let Some(entrypoint) = manifest.entrypoint.clone() else {
+                anyhow::bail!("Exporting the entrypoint as {export_name} requires an entrypoint");
+            };
+            export_function(module, &entrypoint, export_name)?;
+            manifest.rename(&entrypoint, export_name);
+        }
+        manifest.resolve(module);
 
         let module_bitcode = module.write_bitcode_to_memory();
         let module_text = args.output.llvm.as_ref().map(|_| module.to_string());
+        let object = args
+            .output
+            .object
+            .as_ref()
+            .filter(|_| valid)
+            .map(|_| machine.write_to_memory_buffer(module, FileType::Object))
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("Failed to emit object code.\n{e}"))?;
+        let assembly = args
+            .output
+            .asm
+            .as_ref()
+            .filter(|_| valid)
+            .map(|_| machine.write_to_memory_buffer(module, FileType::Assembly))
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("Failed to emit assembly.\n{e}"))?;
 
         Ok(Self {
             module_bitcode,
             module_text,
-            entrypoint: mangled_name,
+            object,
+            assembly,
+            entrypoint: manifest.entrypoint.clone(),
+            manifest,
         })
     }
 }
 
+/// Parse an LLVM module from a text (`.ll`) or bitcode (`.bc`) file,
+/// according to its extension.
+pub fn parse_module<'c>(path: &Path, context: &'c Context) -> anyhow::Result<Module<'c>> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("ll") => parse_ir_file(path, context),
+        Some("bc") => parse_bitcode_file(path, context),
+        _ => anyhow::bail!(
+            "{}: Unknown LLVM module format, expected a `.ll` or `.bc` file",
+            path.display()
+        ),
+    }
+}
+
+/// Parse an LLVM module from a text IR file.
+pub fn parse_ir_file<'c>(path: &Path, context: &'c Context) -> anyhow::Result<Module<'c>> {
+    let error = |e| anyhow::anyhow!("{}: {e}", path.display());
+    let buffer = MemoryBuffer::create_from_file(path).map_err(error)?;
+    context.create_module_from_ir(buffer).map_err(error)
+}
+
+/// Parse an LLVM module from a bitcode file.
+pub fn parse_bitcode_file<'c>(path: &Path, context: &'c Context) -> anyhow::Result<Module<'c>> {
+    Module::parse_bitcode_from_path(path, context)
+        .map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))
+}
+
+/// Lower a HUGR into a new module, with debug information if requested.
+///
+/// The `manifest` holds the symbols given by `namer`.
 fn compile_module<'a>(
     hugr: &Hugr,
     ctx: &'a Context,
-    namer: hugr::llvm::emit::Namer,
+    namer: Namer,
+    manifest: &Manifest,
+    args: &CliArgs,
 ) -> anyhow::Result<Module<'a>> {
     let llvm_module = ctx.create_module("guppy_llvm");
-    // TODO: Handle tket2 codegen extension
-    let extensions = codegen_extensions();
+    let debug_info = args
+        .debug_info
+        .then(|| DebugInfo::new(ctx, &llvm_module, hugr, args.opt != OptimisationLevel::O0))
+        .flatten()
+        .map(Rc::new);
+    let extensions = codegen_extensions(debug_info.clone());
 
     let emitter =
         hugr::llvm::emit::EmitHugr::new(ctx, llvm_module, namer.into(), extensions.into());
     let hugr_module = hugr.fat_root().unwrap();
     let emitter = emitter.emit_module(hugr_module)?;
 
-    Ok(emitter.finish())
+    let module = emitter.finish();
+    if let Some(debug_info) = debug_info {
+        debug_info.finish(&module, manifest);
+    }
+    Ok(module)
+}
+
+/// Give a function a stable symbol name, with external linkage.
+fn export_function(module: &Module<'_>, symbol: &str, export_name: &str) -> anyhow::Result<()> {
+    let Some(func) = module.get_function(symbol) else {
+        anyhow::bail!("Cannot find the function {symbol} in the LLVM module");
+    };
+    if module.get_function(export_name).is_some() || module.get_global(export_name).is_some() {
+        anyhow::bail!("Cannot export {symbol} as {export_name}, the symbol is already defined");
+    }
+    func.as_global_value().set_name(export_name);
+    func.set_linkage(Linkage::External);
+    Ok(())
+}
+
+/// Verify a module, naming the first invalid function in the error.
+fn verify_module(module: &Module<'_>, phase: &str) -> anyhow::Result<()> {
+    let Err(msg) = module.verify() else {
+        return Ok(());
+    };
+    let function = module
+        .get_functions()
+        .find(|f| !f.verify(false))
+        .map(|f| f.get_name().to_string_lossy().into_owned());
+    match function {
+        Some(name) => anyhow::bail!("Invalid LLVM IR in function `{name}` {phase}.\n{msg}"),
+        None => anyhow::bail!("Invalid LLVM IR {phase}.\n{msg}"),
+    }
+}
+
+/// Handle the result of [verify_module].
+///
+/// With `--emit-invalid`, verification errors are logged instead, and
+/// `false` is returned so the broken module can still be stored.
+fn verified(result: anyhow::Result<()>, args: &CliArgs) -> anyhow::Result<bool> {
+    match result {
+        Ok(()) => Ok(true),
+        Err(e) if args.output.emit_invalid => {
+            log::error!("{e}");
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
 }
 
 // Run some standard optimisations on the module.
@@ -115,7 +331,7 @@ fn optimise_module(module: &Module<'_>, args: &CliArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn codegen_extensions() -> CodegenExtsMap<'static, Hugr> {
+fn codegen_extensions(debug_info: Option<Rc<DebugInfo<'_>>>) -> CodegenExtsMap<'_, Hugr> {
     CodegenExtsBuilder::default()
         .add_default_prelude_extensions()
         .add_default_list_extensions()
@@ -124,9 +340,7 @@ fn codegen_extensions() -> CodegenExtsMap<'static, Hugr> {
         .add_float_extensions()
         .add_conversion_extensions()
         .add_logic_extensions()
-        .add_extension(hugr_qir::rotation::RotationCodegenExtension::new(
-            hugr_qir::qir::QirPreludeCodegen,
-        ))
         .add_extension(hugr_qir::qir::QirCodegenExtension)
+        .add_extension(Tket2CodegenExtension { debug_info })
         .finish()
 }
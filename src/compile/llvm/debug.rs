@@ -0,0 +1,246 @@
+//! DWARF debug information pointing the LLVM output back to the guppy source.
+//!
+//! The frontend locates the HUGR nodes compiled from each statement and
+//! expression, and the function definitions at their `def` line. Operations
+//! lowered by [`Tket2CodegenExtension`] set the location of the instructions
+//! emitted after them, the instructions emitted before the first located
+//! operation of a function are located at its definition.
+//!
+//! [`Tket2CodegenExtension`]: super::quantum::Tket2CodegenExtension
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use hugr::llvm::inkwell::builder::Builder;
+use hugr::llvm::inkwell::context::Context;
+use hugr::llvm::inkwell::debug_info::{
+    AsDIScope, DICompileUnit, DIFile, DIFlags, DIFlagsConstants, DILocation, DISubprogram,
+    DWARFEmissionKind, DWARFSourceLanguage, DebugInfoBuilder,
+};
+use hugr::llvm::inkwell::module::{FlagBehavior, Module};
+use hugr::llvm::inkwell::values::InstructionValue;
+use hugr::{Hugr, HugrView, Node};
+
+use super::manifest::Manifest;
+use crate::compile::hugr::functions;
+use crate::compile::hugr::source::{self, SourceLocation};
+
+/// Version of the debug info metadata format emitted by LLVM 14.
+const DEBUG_METADATA_VERSION: u64 = 3;
+
+/// The debug information of a module being lowered from a HUGR.
+pub struct DebugInfo<'c> {
+    context: &'c Context,
+    dibuilder: DebugInfoBuilder<'c>,
+    compile_unit: DICompileUnit<'c>,
+    is_optimized: bool,
+    /// The functions defined in the HUGR with a source location.
+    functions: BTreeMap<Node, LocatedFunction>,
+    files: RefCell<HashMap<String, DIFile<'c>>>,
+    subprograms: RefCell<HashMap<Node, DISubprogram<'c>>>,
+}
+
+impl<'c> DebugInfo<'c> {
+    /// Start the debug information of a module lowered from `hugr`.
+    ///
+    /// Returns `None` if the HUGR has no source locations.
+    pub fn new(
+        context: &'c Context,
+        module: &Module<'c>,
+        hugr: &Hugr,
+        is_optimized: bool,
+    ) -> Option<Self> {
+        let functions = functions::functions(hugr)
+            .into_iter()
+            .filter(|f| !f.is_declaration)
+            .filter_map(|f| {
+                let location = source::source_location(hugr, f.node)?;
+                let function = LocatedFunction {
+                    qualified_name: f.qualified_name,
+                    location,
+                };
+                Some((f.node, function))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let Some(first) = functions.values().next().map(|f| &f.location) else {
+            log::warn!("The Hugr has no source locations, no debug information will be emitted");
+            return None;
+        };
+
+        let (file_name, directory) = split_path(&first.file);
+        let (dibuilder, compile_unit) = module.create_debug_info_builder(
+            true,
+            DWARFSourceLanguage::Python,
+            &file_name,
+            &directory,
+            concat!("guppyc ", env!("CARGO_PKG_VERSION")),
+            is_optimized,
+            "",
+            0,
+            "",
+            DWARFEmissionKind::Full,
+            0,
+            false,
+            false,
+            "",
+            "",
+        );
+        Some(Self {
+            context,
+            dibuilder,
+            compile_unit,
+            is_optimized,
+            functions,
+            files: RefCell::default(),
+            subprograms: RefCell::default(),
+        })
+    }
+
+    /// Returns the location of a HUGR node being lowered into the LLVM
+    /// function `linkage_name`, along with the function's subprogram.
+    ///
+    /// Nodes in functions without a source location are not located.
+    pub fn location(
+        &self,
+        hugr: &impl HugrView,
+        node: Node,
+        linkage_name: &str,
+    ) -> Option<(DISubprogram<'c>, DILocation<'c>)> {
+        let function = functions::enclosing_function(hugr, node)?;
+        let subprogram = self.subprogram(function, linkage_name)?;
+        let location = source::source_location(hugr, node)?;
+        Some((subprogram, self.debug_location(subprogram, &location)))
+    }
+
+    /// Attach a subprogram to every located function of the lowered module,
+    /// and locate the instructions emitted before any located operation at
+    /// their function definition.
+    ///
+    /// The functions are found by their symbol in the `manifest`.
+    pub fn finish(&self, module: &Module<'c>, manifest: &Manifest) {
+        let builder = self.context.create_builder();
+        let dbg = self.context.get_kind_id("dbg");
+        for (&node, function) in &self.functions {
+            let Some(func) = manifest.symbol(node).and_then(|s| module.get_function(s)) else {
+                continue;
+            };
+            let linkage_name = func.get_name().to_string_lossy();
+            let Some(subprogram) = self.subprogram(node, &linkage_name) else {
+                continue;
+            };
+            if func.get_subprogram().is_none() {
+                func.set_subprogram(subprogram);
+            }
+
+            builder.set_current_debug_location(self.debug_location(subprogram, &function.location));
+            for block in func.get_basic_blocks() {
+                let mut inst = block.get_first_instruction();
+                while let Some(i) = inst {
+                    inst = i.get_next_instruction();
+                    if i.get_metadata(dbg).is_none() {
+                        relocate(&builder, i);
+                    }
+                }
+            }
+        }
+        self.dibuilder.finalize();
+
+        module.add_basic_value_flag(
+            "Debug Info Version",
+            FlagBehavior::Warning,
+            self.context
+                .i32_type()
+                .const_int(DEBUG_METADATA_VERSION, false),
+        );
+        module.add_basic_value_flag(
+            "Dwarf Version",
+            FlagBehavior::Warning,
+            self.context.i32_type().const_int(4, false),
+        );
+    }
+
+    /// Returns the subprogram of a located function, creating it on first use.
+    fn subprogram(&self, function: Node, linkage_name: &str) -> Option<DISubprogram<'c>> {
+        let LocatedFunction {
+            qualified_name,
+            location: SourceLocation { file, line, .. },
+            ..
+        } = self.functions.get(&function)?;
+        if let Some(subprogram) = self.subprograms.borrow().get(&function) {
+            return Some(*subprogram);
+        }
+
+        let di_file = *self
+            .files
+            .borrow_mut()
+            .entry(file.clone())
+            .or_insert_with(|| {
+                let (file_name, directory) = split_path(file);
+                self.dibuilder.create_file(&file_name, &directory)
+            });
+        let subroutine_type =
+            self.dibuilder
+                .create_subroutine_type(di_file, None, &[], DIFlags::PUBLIC);
+        let subprogram = self.dibuilder.create_function(
+            self.compile_unit.as_debug_info_scope(),
+            qualified_name,
+            Some(linkage_name),
+            di_file,
+            *line,
+            subroutine_type,
+            false,
+            true,
+            *line,
+            DIFlags::PUBLIC,
+            self.is_optimized,
+        );
+        self.subprograms.borrow_mut().insert(function, subprogram);
+        Some(subprogram)
+    }
+
+    fn debug_location(
+        &self,
+        subprogram: DISubprogram<'c>,
+        location: &SourceLocation,
+    ) -> DILocation<'c> {
+        self.dibuilder.create_debug_location(
+            self.context,
+            location.line,
+            location.column.unwrap_or(0),
+            subprogram.as_debug_info_scope(),
+            None,
+        )
+    }
+}
+
+/// A function defined in the HUGR with a source location.
+struct LocatedFunction {
+    qualified_name: String,
+    location: SourceLocation,
+}
+
+/// Replace an instruction by a copy located at the builder's current location.
+///
+/// LLVM only sets debug locations when inserting instructions through a
+/// builder.
+fn relocate<'c>(builder: &Builder<'c>, inst: InstructionValue<'c>) {
+    let copy = inst.explicit_clone();
+    builder.position_before(&inst);
+    builder.insert_instruction(&copy, None);
+    inst.replace_all_uses_with(&copy);
+    inst.erase_from_basic_block();
+}
+
+/// Split a path into its file name and directory.
+fn split_path(path: &str) -> (String, String) {
+    let path = Path::new(path);
+    let file_name = path
+        .file_name()
+        .map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy());
+    let directory = path
+        .parent()
+        .map(|dir| dir.to_string_lossy())
+        .unwrap_or_default();
+    (file_name.into_owned(), directory.into_owned())
+}
@@ -0,0 +1,134 @@
+//! Linking external LLVM libraries into the output module.
+
+use std::path::{Path, PathBuf};
+
+use derive_more::{Display, Error};
+use hugr::llvm::inkwell::context::Context;
+use hugr::llvm::inkwell::module::{Linkage, Module};
+use hugr::llvm::inkwell::types::AnyType;
+
+use super::parse_module;
+
+/// Link LLVM libraries into a module, in order.
+///
+/// Each library is parsed into `context`, which must be the context of the module.
+pub fn link_libraries<'c>(
+    context: &'c Context,
+    module: &Module<'c>,
+    libraries: &[PathBuf],
+) -> anyhow::Result<()> {
+    for path in libraries {
+        log::debug!("Linking {}", path.display());
+        let library = parse_module(path, context)?;
+        check_symbols(module, &library, path)?;
+        module
+            .link_in_module(library)
+            .map_err(|e| LinkError::Failed {
+                library: path.display().to_string(),
+                msg: e.to_string(),
+            })?;
+    }
+    Ok(())
+}
+
+/// Check that the symbols of a library are compatible with the module.
+///
+/// Symbols with internal or private linkage in either module are renamed by
+/// the linker instead of being merged, so they never conflict.
+fn check_symbols(module: &Module<'_>, library: &Module<'_>, path: &Path) -> Result<(), LinkError> {
+    let library_name = || path.display().to_string();
+    for func in library.get_functions() {
+        let symbol = func.get_name().to_string_lossy();
+        let Some(existing) = module.get_function(&symbol) else {
+            continue;
+        };
+        if is_local(existing.get_linkage()) || is_local(func.get_linkage()) {
+            continue;
+        }
+        if existing.count_basic_blocks() > 0 && func.count_basic_blocks() > 0 {
+            return Err(LinkError::DuplicateSymbol {
+                symbol: symbol.into_owned(),
+                library: library_name(),
+            });
+        }
+        check_type(
+            &symbol,
+            existing.get_type().print_to_string().to_string(),
+            func.get_type().print_to_string().to_string(),
+            path,
+        )?;
+    }
+    for global in library.get_globals() {
+        let symbol = global.get_name().to_string_lossy();
+        let Some(existing) = module.get_global(&symbol) else {
+            continue;
+        };
+        if is_local(existing.get_linkage()) || is_local(global.get_linkage()) {
+            continue;
+        }
+        if !existing.is_declaration() && !global.is_declaration() {
+            return Err(LinkError::DuplicateSymbol {
+                symbol: symbol.into_owned(),
+                library: library_name(),
+            });
+        }
+        check_type(
+            &symbol,
+            existing.get_value_type().print_to_string().to_string(),
+            global.get_value_type().print_to_string().to_string(),
+            path,
+        )?;
+    }
+    Ok(())
+}
+
+/// Check that a symbol has the same type in the module and the library.
+fn check_type(symbol: &str, expected: String, found: String, path: &Path) -> Result<(), LinkError> {
+    if expected == found {
+        return Ok(());
+    }
+    Err(LinkError::TypeMismatch {
+        symbol: symbol.to_string(),
+        library: path.display().to_string(),
+        expected,
+        found,
+    })
+}
+
+/// Returns `true` if a symbol is only visible in its own module.
+fn is_local(linkage: Linkage) -> bool {
+    matches!(linkage, Linkage::Internal | Linkage::Private)
+}
+
+/// An error linking a library into the output module.
+#[derive(Debug, Display, Error)]
+pub enum LinkError {
+    /// The library defines a symbol that is already defined.
+    #[display("Symbol {symbol} is defined both in the program and in {library}")]
+    DuplicateSymbol {
+        /// The symbol name.
+        symbol: String,
+        /// The library path.
+        library: String,
+    },
+    /// The library uses a symbol with a different type.
+    #[display("Symbol {symbol} has type `{expected}` in the program, but `{found}` in {library}")]
+    TypeMismatch {
+        /// The symbol name.
+        symbol: String,
+        /// The library path.
+        library: String,
+        /// The type in the program.
+        expected: String,
+        /// The type in the library.
+        found: String,
+    },
+    /// LLVM failed to link the library.
+    #[display("Failed to link {library}.\n{msg}")]
+    Failed {
+        /// The library path.
+        library: String,
+        /// The LLVM error message.
+        msg: String,
+    },
+}
@@ -0,0 +1,120 @@
+//! Manifest of the symbols in the LLVM output.
+
+use hugr::llvm::emit::Namer;
+use hugr::llvm::inkwell::module::Module;
+use hugr::llvm::inkwell::types::AnyType;
+use hugr::{Hugr, Node, NodeIndex};
+use serde::Serialize;
+
+use crate::compile::hugr::functions;
+
+/// The LLVM symbols of a compiled program, and the HUGR functions they come from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Manifest {
+    /// The symbol of the entrypoint function, if any.
+    pub entrypoint: Option<String>,
+    /// The functions in the module.
+    pub functions: Vec<FunctionSymbol>,
+}
+
+/// A function in the LLVM output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FunctionSymbol {
+    /// The LLVM symbol name.
+    pub symbol: String,
+    /// The LLVM function type, if the function is present in the module.
+    pub llvm_type: Option<String>,
+    /// The LLVM linkage, if the function is present in the module.
+    pub linkage: Option<String>,
+    /// Whether the function is the entrypoint.
+    pub entrypoint: bool,
+    /// The HUGR function lowered into the symbol, if known.
+    pub hugr: Option<HugrFunction>,
+}
+
+/// A function in the HUGR.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HugrFunction {
+    /// The qualified function name.
+    pub name: String,
+    /// The `FuncDefn` node.
+    pub node: usize,
+    /// The HUGR signature of the function.
+    pub signature: String,
+}
+
+impl Manifest {
+    /// Collect the symbols of the functions defined in a HUGR, as named by
+    /// the `namer` of the LLVM lowering.
+    ///
+    /// The `entrypoint` must be a function definition.
+    pub fn from_hugr(hugr: &Hugr, namer: &Namer, entrypoint: Option<Node>) -> Self {
+        let functions = functions::functions(hugr)
+            .into_iter()
+            .filter(|f| !f.is_declaration)
+            .map(|f| FunctionSymbol {
+                symbol: namer.name_func(&f.name, f.node),
+                llvm_type: None,
+                linkage: None,
+                entrypoint: false,
+                hugr: Some(HugrFunction {
+                    name: f.qualified_name,
+                    node: f.node.index(),
+                    signature: f.signature.to_string(),
+                }),
+            })
+            .collect();
+        let mut manifest = Self {
+            entrypoint: None,
+            functions,
+        };
+        manifest.entrypoint = entrypoint
+            .and_then(|node| manifest.symbol(node))
+            .map(str::to_string);
+        manifest
+    }
+
+    /// The symbol of a function defined in the HUGR.
+    pub fn symbol(&self, node: Node) -> Option<&str> {
+        self.functions
+            .iter()
+            .find(|f| f.hugr.as_ref().is_some_and(|h| h.node == node.index()))
+            .map(|f| f.symbol.as_str())
+    }
+
+    /// Rename the symbol of a function.
+    pub fn rename(&mut self, from: &str, to: &str) {
+        for function in &mut self.functions {
+            if function.symbol == from {
+                function.symbol = to.to_string();
+            }
+        }
+        if self.entrypoint.as_deref() == Some(from) {
+            self.entrypoint = Some(to.to_string());
+        }
+    }
+
+    /// Fill in the LLVM types and linkage of the functions from the final
+    /// module, adding the functions defined only in the module.
+    pub fn resolve(&mut self, module: &Module<'_>) {
+        for func in module.get_functions() {
+            let symbol = func.get_name().to_string_lossy();
+            if func.count_basic_blocks() > 0 && !self.functions.iter().any(|f| f.symbol == symbol) {
+                self.functions.push(FunctionSymbol {
+                    symbol: symbol.into_owned(),
+                    llvm_type: None,
+                    linkage: None,
+                    entrypoint: false,
+                    hugr: None,
+                });
+            }
+        }
+        for function in &mut self.functions {
+            function.entrypoint = self.entrypoint.as_ref() == Some(&function.symbol);
+            if let Some(func) = module.get_function(&function.symbol) {
+                function.llvm_type = Some(func.get_type().print_to_string().to_string());
+                function.linkage = Some(format!("{:?}", func.get_linkage()));
+            }
+        }
+    }
+}
@@ -0,0 +1,616 @@
+//! QIR profiles the LLVM output can be made to conform to.
+
+use clap::ValueEnum;
+use derive_more::{Display, Error};
+use hugr::llvm::inkwell::attributes::{Attribute, AttributeLoc};
+use hugr::llvm::inkwell::basic_block::BasicBlock;
+use hugr::llvm::inkwell::builder::Builder;
+use hugr::llvm::inkwell::module::{FlagBehavior, Linkage, Module};
+use hugr::llvm::inkwell::passes::PassManager;
+use hugr::llvm::inkwell::types::AnyType;
+use hugr::llvm::inkwell::values::{
+    BasicMetadataValueEnum, BasicValue, BasicValueEnum, FunctionValue, InstructionOpcode,
+    InstructionValue, PointerValue,
+};
+use hugr::ops::OpType;
+use hugr::{Hugr, HugrView, Node};
+
+use crate::compile::hugr::passes::HugrPass;
+use crate::compile::hugr::source;
+
+pub mod conformance;
+
+use conformance::QirViolation;
+
+/// QIR runtime function allocating a qubit.
+const QUBIT_ALLOCATE: &str = "__quantum__rt__qubit_allocate";
+/// QIR runtime function releasing a qubit.
+const QUBIT_RELEASE: &str = "__quantum__rt__qubit_release";
+/// QIR function resetting a qubit to the zero state.
+const RESET: &str = "__quantum__qis__reset__body";
+/// QIR function measuring a qubit into a dynamically allocated result.
+const MEASURE: &str = "__quantum__qis__m__body";
+/// QIR function measuring a qubit into a given result.
+const MEASURE_INTO: &str = "__quantum__qis__mz__body";
+/// QIR function reading the value of a result.
+const READ_RESULT: &str = "__quantum__qis__read_result__body";
+/// QIR function recording a boolean output.
+const BOOL_RECORD_OUTPUT: &str = "__quantum__rt__bool_record_output";
+/// QIR function recording a measurement result output.
+const RESULT_RECORD_OUTPUT: &str = "__quantum__rt__result_record_output";
+/// QIR function recording an integer output.
+const INT_RECORD_OUTPUT: &str = "__quantum__rt__int_record_output";
+/// QIR function recording a floating-point output.
+const DOUBLE_RECORD_OUTPUT: &str = "__quantum__rt__double_record_output";
+
+/// A QIR profile.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum QirProfile {
+    /// Straight-line programs with statically allocated qubits and results.
+    Base,
+    /// Programs branching on mid-circuit measurements, with integer computations.
+    Adaptive,
+}
+
+impl QirProfile {
+    /// The name of the profile in the `qir_profiles` attribute.
+    pub fn attribute_name(&self) -> &'static str {
+        match self {
+            QirProfile::Base => "base_profile",
+            QirProfile::Adaptive => "adaptive_profile",
+        }
+    }
+
+    /// Returns `true` if the profile can express a control flow construct.
+    fn supports(&self, construct: &ControlFlow) -> bool {
+        match self {
+            QirProfile::Base => false,
+            QirProfile::Adaptive => !matches!(construct, ControlFlow::Call(_)),
+        }
+    }
+
+    /// Transform the HUGR so that its entrypoint can be lowered into the
+    /// profile, or explain why it cannot.
+    pub fn prepare_hugr(&self, hugr: &mut Hugr, entrypoint: Option<Node>) -> anyhow::Result<()> {
+        let Some(entrypoint) = entrypoint else {
+            return Err(QirProfileError::MissingEntrypoint { profile: *self }.into());
+        };
+
+        // The entrypoint must be a single function, with only the control
+        // flow supported by the profile.
+        for pass in [
+            HugrPass::Inline,
+            HugrPass::DeadFuncs,
+            HugrPass::NormalizeDfgs,
+            HugrPass::NormalizeCfgs,
+        ] {
+            pass.run(hugr, Some(entrypoint))?;
+        }
+        if let Some((node, construct)) = control_flow(hugr, entrypoint)
+            .into_iter()
+            .find(|(_, construct)| !self.supports(construct))
+        {
+            let at = match source::source_location(hugr, node) {
+                Some(location) => format!("node {node} ({location})"),
+                None => format!("node {node}"),
+            };
+            return Err(QirProfileError::UnsupportedConstruct {
+                profile: *self,
+                construct: construct.to_string(),
+                at,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Rewrite the entrypoint of a module to follow the profile, and add the
+    /// profile attributes and module flags.
+    ///
+    /// The values returned by the entrypoint are recorded as outputs instead.
+    ///
+    /// Expects the module to be emitted from a HUGR prepared by [QirProfile::prepare_hugr].
+    pub fn apply(&self, module: &Module<'_>, entrypoint: &str) -> anyhow::Result<()> {
+        let Some(entry) = module.get_function(entrypoint) else {
+            anyhow::bail!("Cannot find the entrypoint {entrypoint} in the LLVM module");
+        };
+        let entry = wrap_entrypoint(module, entry, *self)?;
+
+        // Inline the original entrypoint, and fold the HUGR value
+        // representations so the measurement results flow directly into the
+        // output recording calls.
+        let pm = PassManager::create(());
+        pm.add_always_inliner_pass();
+        pm.add_global_dce_pass();
+        pm.add_promote_memory_to_register_pass();
+        pm.add_instruction_combining_pass();
+        pm.add_cfg_simplification_pass();
+        pm.run_on(module);
+
+        let num_qubits = allocate_qubits(module, entry)?;
+        let num_results = allocate_results(module, entry)?;
+        record_results(module, entry, *self)?;
+
+        let pm = PassManager::create(());
+        pm.add_aggressive_dce_pass();
+        pm.run_on(module);
+
+        let context = module.get_context();
+        for (key, value) in [
+            ("EntryPoint", String::new()),
+            ("qir_profiles", self.attribute_name().to_string()),
+            ("output_labeling_schema", "labeled".to_string()),
+            ("required_num_qubits", num_qubits.to_string()),
+            ("required_num_results", num_results.to_string()),
+        ] {
+            let attribute = context.create_string_attribute(key, &value);
+            entry.add_attribute(AttributeLoc::Function, attribute);
+        }
+
+        let i32_t = context.i32_type();
+        let bool_t = context.bool_type();
+        module.add_basic_value_flag(
+            "qir_major_version",
+            FlagBehavior::Error,
+            i32_t.const_int(1, false),
+        );
+        module.add_basic_value_flag(
+            "qir_minor_version",
+            FlagBehavior::Max,
+            i32_t.const_int(0, false),
+        );
+        module.add_basic_value_flag(
+            "dynamic_qubit_management",
+            FlagBehavior::Error,
+            bool_t.const_zero(),
+        );
+        module.add_basic_value_flag(
+            "dynamic_result_management",
+            FlagBehavior::Error,
+            bool_t.const_zero(),
+        );
+        if *self == QirProfile::Adaptive {
+            if instructions(entry)
+                .iter()
+                .any(|&inst| is_int_computation(inst))
+            {
+                let int_width = context.metadata_string("i64");
+                module.add_metadata_flag("int_computations", FlagBehavior::Append, int_width);
+            }
+            if instructions(entry)
+                .iter()
+                .any(|&inst| conformance::is_float_computation(inst))
+            {
+                let float_width = context.metadata_string("f64");
+                module.add_metadata_flag("float_computations", FlagBehavior::Append, float_width);
+            }
+            if has_cycle(entry) {
+                // Loops may run for a fixed or a dynamic number of iterations.
+                let kinds = context.custom_width_int_type(2).const_int(3, false);
+                module.add_basic_value_flag("backwards_branching", FlagBehavior::Max, kinds);
+            }
+        }
+
+        if let Some(violation) = self.check_module(module).into_iter().next() {
+            return Err(QirProfileError::Violation {
+                profile: *self,
+                violation,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// An error preventing a program from following a QIR profile.
+#[derive(Debug, Display, Error)]
+pub enum QirProfileError {
+    /// The profile requires an entrypoint, but none was selected.
+    #[display("The {profile} QIR profile requires an entrypoint function")]
+    MissingEntrypoint {
+        /// The requested profile.
+        profile: QirProfile,
+    },
+    /// The program uses a construct the profile cannot express.
+    #[display("The {profile} QIR profile does not support {construct}, found at {at}")]
+    UnsupportedConstruct {
+        /// The requested profile.
+        profile: QirProfile,
+        /// Description of the unsupported construct.
+        construct: String,
+        /// The offending HUGR node, and its source location if known.
+        at: String,
+    },
+    /// The LLVM module does not conform to the profile.
+    #[display("The {profile} QIR profile is violated: {violation}")]
+    Violation {
+        /// The requested profile.
+        profile: QirProfile,
+        /// The first violation found.
+        violation: QirViolation,
+    },
+}
+
+/// A control flow construct that some profiles cannot express.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+enum ControlFlow {
+    /// A loop, either a `TailLoop` or a cycle in a CFG.
+    #[display("loops")]
+    Loop,
+    /// A `Conditional`, or a CFG block with multiple successors.
+    #[display("conditional branching")]
+    Branch,
+    /// A call to another function, that could not be inlined.
+    #[display("the call to {_0}")]
+    Call(String),
+}
+
+/// Find the control flow constructs in a function, along with their nodes.
+fn control_flow(hugr: &Hugr, func: Node) -> Vec<(Node, ControlFlow)> {
+    let mut constructs = Vec::new();
+    for node in hugr.descendants(func) {
+        match hugr.get_optype(node) {
+            OpType::TailLoop(_) => constructs.push((node, ControlFlow::Loop)),
+            OpType::Conditional(_) => constructs.push((node, ControlFlow::Branch)),
+            OpType::Call(_) => {
+                let callee = hugr
+                    .static_source(node)
+                    .and_then(|f| hugr.get_optype(f).as_func_defn().map(|f| f.name.clone()))
+                    .unwrap_or_default();
+                constructs.push((node, ControlFlow::Call(callee)));
+            }
+            OpType::CFG(_) => {
+                for block in hugr.children(node) {
+                    if reaches(hugr, block, block) {
+                        constructs.push((block, ControlFlow::Loop));
+                    } else if hugr.output_neighbours(block).count() > 1 {
+                        constructs.push((block, ControlFlow::Branch));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    constructs
+}
+
+/// Returns `true` if the `target` CFG block can be reached from the successors of `from`.
+fn reaches(hugr: &Hugr, from: Node, target: Node) -> bool {
+    let mut visited = Vec::new();
+    let mut queue = hugr.output_neighbours(from).collect::<Vec<_>>();
+    while let Some(block) = queue.pop() {
+        if block == target {
+            return true;
+        }
+        if !visited.contains(&block) {
+            visited.push(block);
+            queue.extend(hugr.output_neighbours(block));
+        }
+    }
+    false
+}
+
+/// Returns `true` if the instruction computes on integers.
+fn is_int_computation(inst: InstructionValue<'_>) -> bool {
+    matches!(
+        inst.get_opcode(),
+        InstructionOpcode::Add
+            | InstructionOpcode::Sub
+            | InstructionOpcode::Mul
+            | InstructionOpcode::UDiv
+            | InstructionOpcode::SDiv
+            | InstructionOpcode::URem
+            | InstructionOpcode::SRem
+            | InstructionOpcode::And
+            | InstructionOpcode::Or
+            | InstructionOpcode::Xor
+            | InstructionOpcode::Shl
+            | InstructionOpcode::LShr
+            | InstructionOpcode::AShr
+            | InstructionOpcode::ICmp
+            | InstructionOpcode::ZExt
+            | InstructionOpcode::SExt
+            | InstructionOpcode::Trunc
+    )
+}
+
+/// Returns `true` if the control flow graph of a function contains a cycle.
+fn has_cycle(func: FunctionValue<'_>) -> bool {
+    let successors = |block: BasicBlock<'_>| -> Vec<BasicBlock<'_>> {
+        let Some(terminator) = block.get_terminator() else {
+            return Vec::new();
+        };
+        (0..terminator.get_num_operands())
+            .filter_map(|i| terminator.get_operand(i)?.right())
+            .collect()
+    };
+    // Depth-first search, looking for an edge back into the current path.
+    let mut finished = Vec::new();
+    let mut path = Vec::new();
+    let mut stack = func
+        .get_first_basic_block()
+        .into_iter()
+        .map(|b| (b, 0))
+        .collect::<Vec<_>>();
+    while let Some((block, next)) = stack.pop() {
+        if next == 0 {
+            path.push(block);
+        }
+        match successors(block).get(next) {
+            Some(&succ) => {
+                stack.push((block, next + 1));
+                if path.contains(&succ) {
+                    return true;
+                }
+                if !finished.contains(&succ) {
+                    stack.push((succ, 0));
+                }
+            }
+            None => {
+                path.pop();
+                finished.push(block);
+            }
+        }
+    }
+    false
+}
+
+/// The instructions of a function, in order.
+fn instructions(func: FunctionValue<'_>) -> Vec<InstructionValue<'_>> {
+    let mut instructions = Vec::new();
+    for block in func.get_basic_blocks() {
+        let mut inst = block.get_first_instruction();
+        while let Some(i) = inst {
+            instructions.push(i);
+            inst = i.get_next_instruction();
+        }
+    }
+    instructions
+}
+
+/// The name of the function called by a call instruction.
+fn called_function(inst: InstructionValue<'_>) -> Option<String> {
+    if inst.get_opcode() != InstructionOpcode::Call {
+        return None;
+    }
+    match inst.get_operand(inst.get_num_operands() - 1)?.left()? {
+        BasicValueEnum::PointerValue(callee) => {
+            Some(callee.get_name().to_string_lossy().into_owned())
+        }
+        _ => None,
+    }
+}
+
+/// The calls in a function to the given function.
+fn calls_to<'c>(func: FunctionValue<'c>, name: &str) -> Vec<InstructionValue<'c>> {
+    instructions(func)
+        .into_iter()
+        .filter(|&inst| called_function(inst).is_some_and(|n| n == name))
+        .collect()
+}
+
+/// Replace the entrypoint by a function without parameters, returning `void`
+/// for the base profile or an `i64` exit code for the adaptive profile.
+///
+/// The new entrypoint calls the original one, which is marked for inlining,
+/// and records each of the returned values as an output.
+fn wrap_entrypoint<'c>(
+    module: &Module<'c>,
+    entry: FunctionValue<'c>,
+    profile: QirProfile,
+) -> anyhow::Result<FunctionValue<'c>> {
+    let name = entry.get_name().to_string_lossy().into_owned();
+    if entry.count_params() > 0 {
+        anyhow::bail!("The entrypoint {name} of a QIR program cannot take arguments");
+    }
+    let context = module.get_context();
+    let builder = context.create_builder();
+
+    entry.as_global_value().set_name(&format!("{name}.body"));
+    entry.set_linkage(Linkage::Private);
+    let always_inline =
+        context.create_enum_attribute(Attribute::get_named_enum_kind_id("alwaysinline"), 0);
+    entry.add_attribute(AttributeLoc::Function, always_inline);
+
+    let i64_t = context.i64_type();
+    let fn_t = match profile {
+        QirProfile::Base => context.void_type().fn_type(&[], false),
+        QirProfile::Adaptive => i64_t.fn_type(&[], false),
+    };
+    let wrapper = module.add_function(&name, fn_t, None);
+    if let Some(subprogram) = entry.get_subprogram() {
+        wrapper.set_subprogram(subprogram);
+    }
+    builder.position_at_end(context.append_basic_block(wrapper, "entry"));
+
+    let returned = builder
+        .build_call(entry, &[], "")?
+        .try_as_basic_value()
+        .left();
+    let mut values = Vec::new();
+    if let Some(returned) = returned {
+        returned_scalars(&builder, returned, &mut values)?;
+    }
+
+    for (index, value) in values.into_iter().enumerate() {
+        let adaptive = profile == QirProfile::Adaptive;
+        let record_name = match value {
+            BasicValueEnum::IntValue(v) if v.get_type().get_bit_width() == 1 => BOOL_RECORD_OUTPUT,
+            BasicValueEnum::IntValue(v) if v.get_type().get_bit_width() == 64 && adaptive => {
+                INT_RECORD_OUTPUT
+            }
+            BasicValueEnum::FloatValue(_) if adaptive => DOUBLE_RECORD_OUTPUT,
+            other => anyhow::bail!(
+                "The {profile} QIR profile cannot record the value of type {} returned by {name}",
+                other.get_type().print_to_string()
+            ),
+        };
+        let label = builder
+            .build_global_string_ptr(&index.to_string(), "")?
+            .as_pointer_value();
+        let record = module.get_function(record_name).unwrap_or_else(|| {
+            let fn_t = context
+                .void_type()
+                .fn_type(&[value.get_type().into(), label.get_type().into()], false);
+            module.add_function(record_name, fn_t, None)
+        });
+        let args: [BasicMetadataValueEnum; 2] = [value.into(), label.into()];
+        builder.build_call(record, &args, "")?;
+    }
+
+    match profile {
+        QirProfile::Base => builder.build_return(None)?,
+        QirProfile::Adaptive => builder.build_return(Some(&i64_t.const_zero()))?,
+    };
+    Ok(wrapper)
+}
+
+/// Split a returned value into the scalars to record, looking through the
+/// structs holding multiple return values and the HUGR sum representations.
+fn returned_scalars<'c>(
+    builder: &Builder<'c>,
+    value: BasicValueEnum<'c>,
+    scalars: &mut Vec<BasicValueEnum<'c>>,
+) -> anyhow::Result<()> {
+    match value {
+        BasicValueEnum::StructValue(tuple) => {
+            for i in 0..tuple.get_type().count_fields() {
+                let field = builder.build_extract_value(tuple, i, "")?;
+                returned_scalars(builder, field, scalars)?;
+            }
+        }
+        value => scalars.push(value),
+    }
+    Ok(())
+}
+
+/// Replace the qubit allocations in a function by static addresses, and
+/// the releases by resets of the released qubit.
+///
+/// Each allocation site gets its own address, which is reused by every
+/// iteration of a loop. Linear qubits are released before the next iteration
+/// allocates them again, so the addresses never overlap, and the reset
+/// returns the qubit to the state a fresh allocation would have.
+///
+/// Returns the number of allocated qubits.
+fn allocate_qubits(module: &Module<'_>, func: FunctionValue<'_>) -> anyhow::Result<u64> {
+    let context = module.get_context();
+    let builder = context.create_builder();
+    let i64_t = context.i64_type();
+    let allocations = calls_to(func, QUBIT_ALLOCATE);
+    for (index, alloc) in allocations.iter().enumerate() {
+        let qubit_t = alloc.get_type().into_pointer_type();
+        let address = i64_t
+            .const_int(index as u64, false)
+            .const_to_pointer(qubit_t);
+        let value = PointerValue::try_from(*alloc)
+            .map_err(|_| anyhow::anyhow!("{QUBIT_ALLOCATE} does not return a pointer"))?;
+        value.replace_all_uses_with(address);
+        alloc.erase_from_basic_block();
+    }
+    for release in calls_to(func, QUBIT_RELEASE) {
+        let qubit = release
+            .get_operand(0)
+            .and_then(|op| op.left())
+            .ok_or_else(|| anyhow::anyhow!("{QUBIT_RELEASE} is missing its qubit"))?;
+        let reset = module.get_function(RESET).unwrap_or_else(|| {
+            let fn_t = context
+                .void_type()
+                .fn_type(&[qubit.get_type().into()], false);
+            module.add_function(RESET, fn_t, None)
+        });
+        builder.position_before(&release);
+        builder.build_call(reset, &[qubit.into()], "")?;
+        release.erase_from_basic_block();
+    }
+    Ok(allocations.len() as u64)
+}
+
+/// Replace the measurements in a function by measurements into static
+/// result addresses.
+///
+/// Returns the number of results.
+fn allocate_results(module: &Module<'_>, func: FunctionValue<'_>) -> anyhow::Result<u64> {
+    let context = module.get_context();
+    let builder = context.create_builder();
+    let i64_t = context.i64_type();
+    let measurements = calls_to(func, MEASURE);
+    for (index, measure) in measurements.iter().enumerate() {
+        let result_t = measure.get_type().into_pointer_type();
+        let qubit = measure
+            .get_operand(0)
+            .and_then(|op| op.left())
+            .ok_or_else(|| anyhow::anyhow!("{MEASURE} is missing its qubit"))?;
+        let address = i64_t
+            .const_int(index as u64, false)
+            .const_to_pointer(result_t);
+
+        let measure_into = module.get_function(MEASURE_INTO).unwrap_or_else(|| {
+            let fn_t = context
+                .void_type()
+                .fn_type(&[qubit.get_type().into(), result_t.into()], false);
+            module.add_function(MEASURE_INTO, fn_t, None)
+        });
+        builder.position_before(measure);
+        builder.build_call(measure_into, &[qubit.into(), address.into()], "")?;
+
+        let value = PointerValue::try_from(*measure)
+            .map_err(|_| anyhow::anyhow!("{MEASURE} does not return a pointer"))?;
+        value.replace_all_uses_with(address);
+        measure.erase_from_basic_block();
+    }
+    Ok(measurements.len() as u64)
+}
+
+/// Record measurement results directly, instead of the booleans read from them.
+///
+/// For the base profile, fails if the measurement results are used in
+/// classical computations.
+fn record_results(
+    module: &Module<'_>,
+    func: FunctionValue<'_>,
+    profile: QirProfile,
+) -> anyhow::Result<()> {
+    let context = module.get_context();
+    let builder = context.create_builder();
+    for record in calls_to(func, BOOL_RECORD_OUTPUT) {
+        let operand = |i: u32| record.get_operand(i)?.left();
+        let (Some(bit), Some(label)) = (operand(0), operand(1)) else {
+            anyhow::bail!("{BOOL_RECORD_OUTPUT} is missing its arguments");
+        };
+        let Some(read) = bit
+            .as_instruction_value()
+            .filter(|&read| called_function(read).is_some_and(|n| n == READ_RESULT))
+        else {
+            continue;
+        };
+        let Some(result) = read.get_operand(0).and_then(|op| op.left()) else {
+            anyhow::bail!("{READ_RESULT} is missing its result");
+        };
+
+        let record_result = module
+            .get_function(RESULT_RECORD_OUTPUT)
+            .unwrap_or_else(|| {
+                let fn_t = context
+                    .void_type()
+                    .fn_type(&[result.get_type().into(), label.get_type().into()], false);
+                module.add_function(RESULT_RECORD_OUTPUT, fn_t, None)
+            });
+        builder.position_before(&record);
+        let args: [BasicMetadataValueEnum; 2] = [result.into(), label.into()];
+        builder.build_call(record_result, &args, "")?;
+        record.erase_from_basic_block();
+    }
+
+    for read in calls_to(func, READ_RESULT) {
+        if read.get_first_use().is_none() {
+            read.erase_from_basic_block();
+        } else if profile == QirProfile::Base {
+            anyhow::bail!(
+                "Measurement results are used in classical computations, which the base QIR profile does not support"
+            );
+        }
+    }
+    Ok(())
+}
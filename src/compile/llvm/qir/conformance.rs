@@ -0,0 +1,368 @@
+//! Conformance of LLVM modules to the QIR profiles.
+
+use std::fmt;
+
+use hugr::llvm::inkwell::attributes::AttributeLoc;
+use hugr::llvm::inkwell::module::Module;
+use hugr::llvm::inkwell::types::AnyType;
+use hugr::llvm::inkwell::values::{AnyValue, FunctionValue, InstructionOpcode, InstructionValue};
+use serde::Serialize;
+
+use super::{
+    MEASURE, QUBIT_ALLOCATE, QUBIT_RELEASE, QirProfile, called_function, has_cycle, instructions,
+    is_int_computation,
+};
+
+/// Module flags required by every profile.
+const REQUIRED_FLAGS: &[&str] = &[
+    "qir_major_version",
+    "qir_minor_version",
+    "dynamic_qubit_management",
+    "dynamic_result_management",
+];
+
+/// Expected signatures of the QIS and runtime functions, as printed by LLVM.
+const INTRINSIC_SIGNATURES: &[(&str, &str)] = &[
+    ("__quantum__qis__h__body", "void (%Qubit*)"),
+    ("__quantum__qis__x__body", "void (%Qubit*)"),
+    ("__quantum__qis__y__body", "void (%Qubit*)"),
+    ("__quantum__qis__z__body", "void (%Qubit*)"),
+    ("__quantum__qis__s__body", "void (%Qubit*)"),
+    ("__quantum__qis__s__adj", "void (%Qubit*)"),
+    ("__quantum__qis__t__body", "void (%Qubit*)"),
+    ("__quantum__qis__t__adj", "void (%Qubit*)"),
+    ("__quantum__qis__reset__body", "void (%Qubit*)"),
+    ("__quantum__qis__cnot__body", "void (%Qubit*, %Qubit*)"),
+    ("__quantum__qis__cy__body", "void (%Qubit*, %Qubit*)"),
+    ("__quantum__qis__cz__body", "void (%Qubit*, %Qubit*)"),
+    (
+        "__quantum__qis__ccx__body",
+        "void (%Qubit*, %Qubit*, %Qubit*)",
+    ),
+    ("__quantum__qis__rx__body", "void (double, %Qubit*)"),
+    ("__quantum__qis__ry__body", "void (double, %Qubit*)"),
+    ("__quantum__qis__rz__body", "void (double, %Qubit*)"),
+    (
+        "__quantum__qis__crz__body",
+        "void (double, %Qubit*, %Qubit*)",
+    ),
+    ("__quantum__qis__mz__body", "void (%Qubit*, %Result*)"),
+    ("__quantum__qis__read_result__body", "i1 (%Result*)"),
+    (
+        "__quantum__rt__result_record_output",
+        "void (%Result*, i8*)",
+    ),
+    ("__quantum__rt__bool_record_output", "void (i1, i8*)"),
+    ("__quantum__rt__int_record_output", "void (i64, i8*)"),
+    ("__quantum__rt__double_record_output", "void (double, i8*)"),
+    ("__quantum__rt__array_record_output", "void (i64, i8*)"),
+    ("__quantum__rt__tuple_record_output", "void (i64, i8*)"),
+];
+
+/// A rule of the QIR profiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, strum::Display)]
+#[serde(rename_all = "snake_case")]
+pub enum QirRule {
+    /// The module must have exactly one entrypoint.
+    #[strum(to_string = "module must have a single entrypoint")]
+    EntryPoint,
+    /// Only the entrypoint may be defined in the module.
+    #[strum(to_string = "only the entrypoint may have a body")]
+    Definition,
+    /// The entrypoint must take no arguments, and return nothing for the base
+    /// profile or an exit code for the adaptive profile.
+    #[strum(to_string = "entrypoint has the wrong signature")]
+    EntrySignature,
+    /// The entrypoint must declare the profile and its resources.
+    #[strum(to_string = "entrypoint attribute is missing or invalid")]
+    Attribute,
+    /// The module must declare the QIR version and capabilities.
+    #[strum(to_string = "module flag is missing")]
+    ModuleFlag,
+    /// The instruction is not allowed by the profile or its declared capabilities.
+    #[strum(to_string = "instruction is not allowed")]
+    Instruction,
+    /// The control flow is not allowed by the profile or its declared capabilities.
+    #[strum(to_string = "branching is not allowed")]
+    Branching,
+    /// Only QIS and runtime functions may be called.
+    #[strum(to_string = "call to a function outside the QIS and runtime")]
+    Call,
+    /// QIS and runtime functions must have their standard signature.
+    #[strum(to_string = "intrinsic has the wrong signature")]
+    Signature,
+    /// Qubits and results must have static addresses.
+    #[strum(to_string = "qubits and results must be allocated statically")]
+    DynamicManagement,
+}
+
+/// A violation of a QIR profile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QirViolation {
+    /// The violated rule.
+    pub rule: QirRule,
+    /// Details about the violation.
+    pub detail: String,
+    /// The offending function, if any.
+    pub function: Option<String>,
+    /// The offending instruction, if any.
+    pub instruction: Option<String>,
+}
+
+impl QirViolation {
+    fn new(rule: QirRule, detail: impl Into<String>) -> Self {
+        Self {
+            rule,
+            detail: detail.into(),
+            function: None,
+            instruction: None,
+        }
+    }
+
+    fn in_function(mut self, func: FunctionValue<'_>) -> Self {
+        self.function = Some(func.get_name().to_string_lossy().into_owned());
+        self
+    }
+
+    fn at(mut self, inst: InstructionValue<'_>) -> Self {
+        self.instruction = Some(inst.print_to_string().to_string().trim().to_string());
+        self
+    }
+}
+
+impl fmt::Display for QirViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.rule, self.detail)?;
+        if let Some(function) = &self.function {
+            write!(f, " in function {function}")?;
+        }
+        if let Some(instruction) = &self.instruction {
+            write!(f, " (`{instruction}`)")?;
+        }
+        Ok(())
+    }
+}
+
+impl QirProfile {
+    /// Check an LLVM module against the rules of the profile.
+    pub fn check_module(&self, module: &Module<'_>) -> Vec<QirViolation> {
+        let mut violations = Vec::new();
+        let has_flag = |key: &str| module.get_flag(key).is_some();
+
+        let entrypoints = module
+            .get_functions()
+            .filter(|f| {
+                f.get_string_attribute(AttributeLoc::Function, "EntryPoint")
+                    .is_some()
+            })
+            .collect::<Vec<_>>();
+        if entrypoints.len() != 1 {
+            violations.push(QirViolation::new(
+                QirRule::EntryPoint,
+                format!(
+                    "found {} functions with the `EntryPoint` attribute",
+                    entrypoints.len()
+                ),
+            ));
+        }
+        for &entry in &entrypoints {
+            self.check_entry_signature(entry, &mut violations);
+            self.check_attributes(entry, &mut violations);
+        }
+
+        for &flag in REQUIRED_FLAGS {
+            if !has_flag(flag) {
+                violations.push(QirViolation::new(
+                    QirRule::ModuleFlag,
+                    format!("`{flag}` is not set"),
+                ));
+            }
+        }
+
+        for func in module.get_functions() {
+            if func.count_basic_blocks() == 0 {
+                check_signature(func, &mut violations);
+            } else if !entrypoints.contains(&func) {
+                violations.push(
+                    QirViolation::new(QirRule::Definition, "function is defined").in_function(func),
+                );
+            } else {
+                self.check_body(func, &has_flag, &mut violations);
+            }
+        }
+        violations
+    }
+
+    /// Check that an entrypoint takes no arguments, and returns `void` for
+    /// the base profile or an `i64` for the adaptive profile.
+    fn check_entry_signature(&self, entry: FunctionValue<'_>, violations: &mut Vec<QirViolation>) {
+        let expected = match self {
+            QirProfile::Base => "void ()",
+            QirProfile::Adaptive => "i64 ()",
+        };
+        let found = entry.get_type().print_to_string().to_string();
+        if found != expected {
+            violations.push(
+                QirViolation::new(
+                    QirRule::EntrySignature,
+                    format!("expected `{expected}`, found `{found}`"),
+                )
+                .in_function(entry),
+            );
+        }
+    }
+
+    /// Check the profile attributes of an entrypoint.
+    fn check_attributes(&self, entry: FunctionValue<'_>, violations: &mut Vec<QirViolation>) {
+        let attribute = |key: &str| {
+            entry
+                .get_string_attribute(AttributeLoc::Function, key)
+                .map(|a| a.get_string_value().to_string_lossy().into_owned())
+        };
+        match attribute("qir_profiles") {
+            Some(profile) if profile == self.attribute_name() => {}
+            Some(profile) => violations.push(
+                QirViolation::new(
+                    QirRule::Attribute,
+                    format!(
+                        "`qir_profiles` is `{profile}`, expected `{}`",
+                        self.attribute_name()
+                    ),
+                )
+                .in_function(entry),
+            ),
+            None => violations.push(
+                QirViolation::new(QirRule::Attribute, "`qir_profiles` is not set")
+                    .in_function(entry),
+            ),
+        }
+        for key in ["required_num_qubits", "required_num_results"] {
+            if attribute(key).is_none_or(|value| value.parse::<u64>().is_err()) {
+                violations.push(
+                    QirViolation::new(
+                        QirRule::Attribute,
+                        format!("`{key}` is not set to a number"),
+                    )
+                    .in_function(entry),
+                );
+            }
+        }
+    }
+
+    /// Check the instructions and control flow of an entrypoint.
+    fn check_body(
+        &self,
+        func: FunctionValue<'_>,
+        has_flag: &impl Fn(&str) -> bool,
+        violations: &mut Vec<QirViolation>,
+    ) {
+        let adaptive = *self == QirProfile::Adaptive;
+        // Capabilities of the adaptive profile, enabled by module flags.
+        let capability = |flag: &str| adaptive && has_flag(flag);
+
+        if has_cycle(func) && !capability("backwards_branching") {
+            violations.push(
+                QirViolation::new(QirRule::Branching, "the control flow contains loops")
+                    .in_function(func),
+            );
+        }
+
+        for inst in instructions(func) {
+            let violation = match inst.get_opcode() {
+                InstructionOpcode::Call => match called_function(inst) {
+                    Some(name)
+                        if [QUBIT_ALLOCATE, QUBIT_RELEASE, MEASURE].contains(&name.as_str()) =>
+                    {
+                        Some(QirViolation::new(
+                            QirRule::DynamicManagement,
+                            format!("`{name}` manages qubits or results dynamically"),
+                        ))
+                    }
+                    Some(name)
+                        if name.starts_with("__quantum__qis__")
+                            || name.starts_with("__quantum__rt__") =>
+                    {
+                        None
+                    }
+                    Some(name) => Some(QirViolation::new(
+                        QirRule::Call,
+                        format!("`{name}` is called"),
+                    )),
+                    None => Some(QirViolation::new(
+                        QirRule::Instruction,
+                        "indirect calls are not allowed",
+                    )),
+                },
+                // Unconditional branches only have the target block as operand.
+                InstructionOpcode::Br if inst.get_num_operands() > 1 && !adaptive => Some(
+                    QirViolation::new(QirRule::Branching, "conditional branches are not allowed"),
+                ),
+                InstructionOpcode::Switch if !capability("multiple_target_branching") => {
+                    Some(QirViolation::new(
+                        QirRule::Branching,
+                        "switches require the `multiple_target_branching` capability",
+                    ))
+                }
+                InstructionOpcode::Br | InstructionOpcode::Switch | InstructionOpcode::Return => {
+                    None
+                }
+                InstructionOpcode::Phi | InstructionOpcode::Select if adaptive => None,
+                _ if is_int_computation(inst) && capability("int_computations") => None,
+                _ if is_int_computation(inst) => Some(QirViolation::new(
+                    QirRule::Instruction,
+                    "integer computations require the `int_computations` capability",
+                )),
+                _ if is_float_computation(inst) && capability("float_computations") => None,
+                _ if is_float_computation(inst) => Some(QirViolation::new(
+                    QirRule::Instruction,
+                    "floating-point computations require the `float_computations` capability",
+                )),
+                opcode => Some(QirViolation::new(
+                    QirRule::Instruction,
+                    format!("{opcode:?} instructions are not allowed"),
+                )),
+            };
+            if let Some(violation) = violation {
+                violations.push(violation.in_function(func).at(inst));
+            }
+        }
+    }
+}
+
+/// Check the signature of a declared QIS or runtime function.
+fn check_signature(func: FunctionValue<'_>, violations: &mut Vec<QirViolation>) {
+    let name = func.get_name().to_string_lossy();
+    let Some((_, expected)) = INTRINSIC_SIGNATURES.iter().find(|(n, _)| *n == name) else {
+        return;
+    };
+    let found = func.get_type().print_to_string().to_string();
+    if found != *expected {
+        violations.push(
+            QirViolation::new(
+                QirRule::Signature,
+                format!("expected `{expected}`, found `{found}`"),
+            )
+            .in_function(func),
+        );
+    }
+}
+
+/// Returns `true` if the instruction computes on floating-point numbers.
+pub(super) fn is_float_computation(inst: InstructionValue<'_>) -> bool {
+    matches!(
+        inst.get_opcode(),
+        InstructionOpcode::FAdd
+            | InstructionOpcode::FSub
+            | InstructionOpcode::FMul
+            | InstructionOpcode::FDiv
+            | InstructionOpcode::FRem
+            | InstructionOpcode::FNeg
+            | InstructionOpcode::FCmp
+            | InstructionOpcode::FPExt
+            | InstructionOpcode::FPTrunc
+            | InstructionOpcode::SIToFP
+            | InstructionOpcode::UIToFP
+            | InstructionOpcode::FPToSI
+            | InstructionOpcode::FPToUI
+    )
+}
@@ -0,0 +1,445 @@
+//! Codegen for the tket2 quantum, rotation, futures and result extensions,
+//! targeting QIR.
+
+use std::rc::Rc;
+
+use anyhow::{Context as _, anyhow};
+use hugr::HugrView;
+use hugr::extension::prelude::{option_type, qb_t};
+use hugr::llvm::CodegenExtension;
+use hugr::llvm::custom::CodegenExtsBuilder;
+use hugr::llvm::emit::{EmitFuncContext, EmitOpArgs};
+use hugr::llvm::inkwell::AddressSpace;
+use hugr::llvm::inkwell::types::{BasicType, BasicTypeEnum};
+use hugr::llvm::inkwell::values::{BasicMetadataValueEnum, BasicValueEnum, FloatValue, IntValue};
+use hugr::llvm::inkwell::{FloatPredicate, IntPredicate};
+use hugr::llvm::sum::LLVMSumValue;
+use hugr::ops::ExtensionOp;
+use hugr::types::{SumType, TypeArg};
+use tket2::Tk2Op;
+use tket2::extension::rotation::{
+    ROTATION_EXTENSION_ID, ROTATION_TYPE_ID, RotationOp, rotation_type,
+};
+use tket2_hseries::extension::futures::{self, FutureOpDef};
+use tket2_hseries::extension::result::{self, ResultOpDef};
+
+use super::debug::DebugInfo;
+
+/// Codegen extension lowering every operation of the tket2 quantum, rotation,
+/// futures and result extensions into QIR calls.
+pub struct Tket2CodegenExtension<'d> {
+    /// Debug information locating the lowered operations, if any.
+    pub debug_info: Option<Rc<DebugInfo<'d>>>,
+}
+
+impl<'d> CodegenExtension for Tket2CodegenExtension<'d> {
+    fn add_extension<'a, H: HugrView + 'a>(
+        self,
+        builder: CodegenExtsBuilder<'a, H>,
+    ) -> CodegenExtsBuilder<'a, H>
+    where
+        Self: 'a,
+    {
+        let quantum_debug = self.debug_info.clone();
+        let rotation_debug = self.debug_info.clone();
+        let futures_debug = self.debug_info.clone();
+        let result_debug = self.debug_info;
+        builder
+            .simple_extension_op::<Tk2Op>(move |context, args, op| {
+                set_debug_location(context, &args, quantum_debug.as_deref());
+                emit_quantum_op(context, args, op)
+            })
+            .custom_type((ROTATION_EXTENSION_ID, ROTATION_TYPE_ID), |session, _| {
+                Ok(session.iw_context().f64_type().as_basic_type_enum())
+            })
+            .simple_extension_op::<RotationOp>(move |context, args, op| {
+                set_debug_location(context, &args, rotation_debug.as_deref());
+                emit_rotation_op(context, args, op)
+            })
+            .custom_type(
+                (futures::EXTENSION_ID, futures::FUTURE_TYPE_NAME.clone()),
+                |session, custom_type| {
+                    // Futures are resolved eagerly, so they are represented by their value.
+                    let [TypeArg::Type { ty }] = custom_type.args() else {
+                        return Err(anyhow!("Invalid future type arguments: {custom_type}"));
+                    };
+                    session.llvm_type(ty)
+                },
+            )
+            .simple_extension_op::<FutureOpDef>(move |context, args, op| {
+                set_debug_location(context, &args, futures_debug.as_deref());
+                let value = args.inputs[0];
+                let outputs = match op {
+                    FutureOpDef::Read => vec![value],
+                    FutureOpDef::Dup => vec![value, value],
+                    FutureOpDef::Free => vec![],
+                };
+                args.outputs.finish(context.builder(), outputs)
+            })
+            .simple_extension_op::<ResultOpDef>(move |context, args, op| {
+                set_debug_location(context, &args, result_debug.as_deref());
+                emit_result_op(context, args, op)
+            })
+    }
+}
+
+/// Locate the instructions emitted from now on at the source of the lowered
+/// operation, attaching the subprogram to the function on first use.
+fn set_debug_location<'c, 'd: 'c, H: HugrView>(
+    context: &mut EmitFuncContext<'c, '_, H>,
+    args: &EmitOpArgs<'c, '_, ExtensionOp, H>,
+    debug_info: Option<&DebugInfo<'d>>,
+) {
+    let Some(debug_info) = debug_info else {
+        return;
+    };
+    let Some(func) = context
+        .builder()
+        .get_insert_block()
+        .and_then(|block| block.get_parent())
+    else {
+        return;
+    };
+    let node = args.node();
+    let linkage_name = func.get_name().to_string_lossy();
+    if let Some((subprogram, location)) =
+        debug_info.location(node.hugr(), node.node(), &linkage_name)
+    {
+        if func.get_subprogram().is_none() {
+            func.set_subprogram(subprogram);
+        }
+        context.builder().set_current_debug_location(location);
+    }
+}
+
+/// Emit a call to a QIR function with the given signature.
+fn emit_qir_call<'c, H: HugrView>(
+    context: &mut EmitFuncContext<'c, '_, H>,
+    name: &str,
+    params: &[BasicTypeEnum<'c>],
+    ret: Option<BasicTypeEnum<'c>>,
+    args: &[BasicMetadataValueEnum<'c>],
+) -> anyhow::Result<Option<BasicValueEnum<'c>>> {
+    let params = params.iter().map(|&t| t.into()).collect::<Vec<_>>();
+    let fn_type = match ret {
+        Some(ret) => ret.fn_type(&params, false),
+        None => context.iw_context().void_type().fn_type(&params, false),
+    };
+    let func = context.get_extern_func(name, fn_type)?;
+    let call = context.builder().build_call(func, args, "")?;
+    Ok(call.try_as_basic_value().left())
+}
+
+/// Convert an LLVM `i1` into a HUGR boolean.
+fn bool_from_i1<'c, H: HugrView>(
+    context: &mut EmitFuncContext<'c, '_, H>,
+    value: IntValue<'c>,
+) -> anyhow::Result<BasicValueEnum<'c>> {
+    let sum_type = context.llvm_sum_type(SumType::new_unary(2))?;
+    let true_val = sum_type.build_tag(context.builder(), 1, vec![])?;
+    let false_val = sum_type.build_tag(context.builder(), 0, vec![])?;
+    Ok(context
+        .builder()
+        .build_select(value, true_val, false_val, "")?)
+}
+
+/// Convert a HUGR boolean into an LLVM `i1`.
+fn i1_from_bool<'c, H: HugrView>(
+    context: &mut EmitFuncContext<'c, '_, H>,
+    value: BasicValueEnum<'c>,
+) -> anyhow::Result<IntValue<'c>> {
+    let sum_type = context.llvm_sum_type(SumType::new_unary(2))?;
+    let tag = LLVMSumValue::try_new(value, sum_type)?.build_get_tag(context.builder())?;
+    let zero = tag.get_type().const_zero();
+    Ok(context
+        .builder()
+        .build_int_compare(IntPredicate::NE, tag, zero, "")?)
+}
+
+/// Convert an angle in half-turns into radians.
+fn radians<'c, H: HugrView>(
+    context: &mut EmitFuncContext<'c, '_, H>,
+    half_turns: BasicValueEnum<'c>,
+) -> anyhow::Result<FloatValue<'c>> {
+    let half_turns = half_turns.into_float_value();
+    let pi = half_turns.get_type().const_float(std::f64::consts::PI);
+    Ok(context.builder().build_float_mul(half_turns, pi, "")?)
+}
+
+/// Types of the QIR function parameters.
+struct QirTypes<'c> {
+    qubit: BasicTypeEnum<'c>,
+    result: BasicTypeEnum<'c>,
+    f64: BasicTypeEnum<'c>,
+    i1: BasicTypeEnum<'c>,
+}
+
+impl<'c> QirTypes<'c> {
+    fn new<H: HugrView>(context: &EmitFuncContext<'c, '_, H>) -> anyhow::Result<Self> {
+        let iw_context = context.iw_context();
+        Ok(Self {
+            qubit: context.llvm_type(&qb_t())?,
+            result: iw_context
+                .opaque_struct_type("Result")
+                .ptr_type(AddressSpace::default())
+                .into(),
+            f64: iw_context.f64_type().into(),
+            i1: iw_context.bool_type().into(),
+        })
+    }
+}
+
+fn emit_quantum_op<'c, H: HugrView>(
+    context: &mut EmitFuncContext<'c, '_, H>,
+    args: EmitOpArgs<'c, '_, ExtensionOp, H>,
+    op: Tk2Op,
+) -> anyhow::Result<()> {
+    let types = QirTypes::new(context)?;
+    match op {
+        Tk2Op::H => emit_gate(context, args, &types, "__quantum__qis__h__body", 1),
+        Tk2Op::X => emit_gate(context, args, &types, "__quantum__qis__x__body", 1),
+        Tk2Op::Y => emit_gate(context, args, &types, "__quantum__qis__y__body", 1),
+        Tk2Op::Z => emit_gate(context, args, &types, "__quantum__qis__z__body", 1),
+        Tk2Op::S => emit_gate(context, args, &types, "__quantum__qis__s__body", 1),
+        Tk2Op::Sdg => emit_gate(context, args, &types, "__quantum__qis__s__adj", 1),
+        Tk2Op::T => emit_gate(context, args, &types, "__quantum__qis__t__body", 1),
+        Tk2Op::Tdg => emit_gate(context, args, &types, "__quantum__qis__t__adj", 1),
+        Tk2Op::Reset => emit_gate(context, args, &types, "__quantum__qis__reset__body", 1),
+        Tk2Op::CX => emit_gate(context, args, &types, "__quantum__qis__cnot__body", 2),
+        Tk2Op::CY => emit_gate(context, args, &types, "__quantum__qis__cy__body", 2),
+        Tk2Op::CZ => emit_gate(context, args, &types, "__quantum__qis__cz__body", 2),
+        Tk2Op::Toffoli => emit_gate(context, args, &types, "__quantum__qis__ccx__body", 3),
+        Tk2Op::Rx => emit_rotation(context, args, &types, "__quantum__qis__rx__body", 1),
+        Tk2Op::Ry => emit_rotation(context, args, &types, "__quantum__qis__ry__body", 1),
+        Tk2Op::Rz => emit_rotation(context, args, &types, "__quantum__qis__rz__body", 1),
+        Tk2Op::CRz => emit_rotation(context, args, &types, "__quantum__qis__crz__body", 2),
+        Tk2Op::Measure | Tk2Op::MeasureFree => {
+            let qubit = args.inputs[0];
+            let result = emit_qir_call(
+                context,
+                "__quantum__qis__m__body",
+                &[types.qubit],
+                Some(types.result),
+                &[qubit.into()],
+            )?
+            .context("Measurement did not return a result")?;
+            let bit = emit_qir_call(
+                context,
+                "__quantum__qis__read_result__body",
+                &[types.result],
+                Some(types.i1),
+                &[result.into()],
+            )?
+            .context("Reading a result did not return a value")?;
+            let bit = bool_from_i1(context, bit.into_int_value())?;
+            if op == Tk2Op::Measure {
+                return args.outputs.finish(context.builder(), [qubit, bit]);
+            }
+            emit_qir_call(
+                context,
+                "__quantum__rt__qubit_release",
+                &[types.qubit],
+                None,
+                &[qubit.into()],
+            )?;
+            args.outputs.finish(context.builder(), [bit])
+        }
+        Tk2Op::QAlloc | Tk2Op::TryQAlloc => {
+            let qubit = emit_qir_call(
+                context,
+                "__quantum__rt__qubit_allocate",
+                &[],
+                Some(types.qubit),
+                &[],
+            )?
+            .context("Qubit allocation did not return a qubit")?;
+            if op == Tk2Op::QAlloc {
+                return args.outputs.finish(context.builder(), [qubit]);
+            }
+            // Allocation cannot fail in QIR, so we always return the `Some` variant.
+            let sum_type = context.llvm_sum_type(option_type(qb_t()))?;
+            let some = sum_type.build_tag(context.builder(), 1, vec![qubit])?;
+            args.outputs.finish(context.builder(), [some])
+        }
+        Tk2Op::QFree => {
+            emit_qir_call(
+                context,
+                "__quantum__rt__qubit_release",
+                &[types.qubit],
+                None,
+                &[args.inputs[0].into()],
+            )?;
+            args.outputs.finish(context.builder(), [])
+        }
+        // `Tk2Op` is non-exhaustive, operations added upstream need a lowering here.
+        _ => Err(anyhow!("Unsupported tket2 quantum operation {op:?}")),
+    }
+}
+
+/// Emit a gate acting only on qubits, which are returned unchanged.
+fn emit_gate<'c, H: HugrView>(
+    context: &mut EmitFuncContext<'c, '_, H>,
+    args: EmitOpArgs<'c, '_, ExtensionOp, H>,
+    types: &QirTypes<'c>,
+    name: &str,
+    n_qubits: usize,
+) -> anyhow::Result<()> {
+    let qubits = args.inputs[0..n_qubits].to_vec();
+    let call_args = qubits
+        .iter()
+        .map(|&q| BasicMetadataValueEnum::from(q))
+        .collect::<Vec<_>>();
+    emit_qir_call(
+        context,
+        name,
+        &vec![types.qubit; n_qubits],
+        None,
+        &call_args,
+    )?;
+    args.outputs.finish(context.builder(), qubits)
+}
+
+/// Emit a rotation gate.
+///
+/// The operation takes the qubits followed by the angle, QIR expects the angle first.
+fn emit_rotation<'c, H: HugrView>(
+    context: &mut EmitFuncContext<'c, '_, H>,
+    args: EmitOpArgs<'c, '_, ExtensionOp, H>,
+    types: &QirTypes<'c>,
+    name: &str,
+    n_qubits: usize,
+) -> anyhow::Result<()> {
+    let qubits = args.inputs[0..n_qubits].to_vec();
+    let angle = radians(context, args.inputs[n_qubits])?;
+    let mut call_args = vec![angle.into()];
+    call_args.extend(qubits.iter().map(|&q| BasicMetadataValueEnum::from(q)));
+    let mut params = vec![types.f64];
+    params.extend(vec![types.qubit; n_qubits]);
+    emit_qir_call(context, name, &params, None, &call_args)?;
+    args.outputs.finish(context.builder(), qubits)
+}
+
+/// Emit an operation of the tket2 rotation extension.
+///
+/// Rotations are represented by their angle in half-turns, as an `f64`.
+fn emit_rotation_op<'c, H: HugrView>(
+    context: &mut EmitFuncContext<'c, '_, H>,
+    args: EmitOpArgs<'c, '_, ExtensionOp, H>,
+    op: RotationOp,
+) -> anyhow::Result<()> {
+    let builder = context.builder();
+    match op {
+        RotationOp::from_halfturns_unchecked | RotationOp::to_halfturns => {
+            args.outputs.finish(builder, [args.inputs[0]])
+        }
+        RotationOp::from_halfturns => {
+            // Only finite angles are valid rotations.
+            let half_turns = args.inputs[0].into_float_value();
+            let difference = builder.build_float_sub(half_turns, half_turns, "")?;
+            let is_finite = builder.build_float_compare(
+                FloatPredicate::OEQ,
+                difference,
+                half_turns.get_type().const_zero(),
+                "",
+            )?;
+            let sum_type = context.llvm_sum_type(option_type(rotation_type()))?;
+            let some = sum_type.build_tag(builder, 1, vec![half_turns.into()])?;
+            let none = sum_type.build_tag(builder, 0, vec![])?;
+            let rotation = builder.build_select(is_finite, some, none, "")?;
+            args.outputs.finish(builder, [rotation])
+        }
+        RotationOp::radd => {
+            let [a, b] = [args.inputs[0], args.inputs[1]].map(|v| v.into_float_value());
+            let sum = builder.build_float_add(a, b, "")?;
+            args.outputs.finish(builder, [sum.into()])
+        }
+        _ => Err(anyhow!("Unsupported tket2 rotation operation {op:?}")),
+    }
+}
+
+fn emit_result_op<'c, H: HugrView>(
+    context: &mut EmitFuncContext<'c, '_, H>,
+    args: EmitOpArgs<'c, '_, ExtensionOp, H>,
+    op: ResultOpDef,
+) -> anyhow::Result<()> {
+    let Some(TypeArg::String { arg: tag }) = args.node().args().first() else {
+        return Err(anyhow!("Result operation {op:?} is missing its tag"));
+    };
+    let label = context
+        .builder()
+        .build_global_string_ptr(tag, "res_tag")?
+        .as_pointer_value();
+    let iw_context = context.iw_context();
+    let label_t = iw_context.i8_type().ptr_type(AddressSpace::default());
+    let i64_t: BasicTypeEnum = iw_context.i64_type().into();
+    let f64_t: BasicTypeEnum = iw_context.f64_type().into();
+    let i1_t: BasicTypeEnum = iw_context.bool_type().into();
+
+    // Each recorded value, along with the recording function and its value type.
+    let value = args.inputs[0];
+    let (record, value_t, values) = match op {
+        ResultOpDef::Bool => {
+            let bit = i1_from_bool(context, value)?;
+            ("__quantum__rt__bool_record_output", i1_t, vec![bit.into()])
+        }
+        ResultOpDef::Int | ResultOpDef::UInt => {
+            ("__quantum__rt__int_record_output", i64_t, vec![value])
+        }
+        ResultOpDef::F64 => ("__quantum__rt__double_record_output", f64_t, vec![value]),
+        ResultOpDef::ArrBool | ResultOpDef::ArrInt | ResultOpDef::ArrUInt | ResultOpDef::ArrF64 => {
+            let array = value.into_array_value();
+            let len = array.get_type().len();
+            let mut elems = Vec::with_capacity(len as usize);
+            for i in 0..len {
+                let elem = context.builder().build_extract_value(array, i, "")?;
+                elems.push(elem);
+            }
+            emit_qir_call(
+                context,
+                "__quantum__rt__array_record_output",
+                &[i64_t, label_t.into()],
+                None,
+                &[
+                    context
+                        .iw_context()
+                        .i64_type()
+                        .const_int(len as u64, false)
+                        .into(),
+                    label.into(),
+                ],
+            )?;
+            let (record, value_t) = match op {
+                ResultOpDef::ArrBool => ("__quantum__rt__bool_record_output", i1_t),
+                ResultOpDef::ArrF64 => ("__quantum__rt__double_record_output", f64_t),
+                _ => ("__quantum__rt__int_record_output", i64_t),
+            };
+            let elems = if op == ResultOpDef::ArrBool {
+                elems
+                    .into_iter()
+                    .map(|e| i1_from_bool(context, e).map(Into::into))
+                    .collect::<anyhow::Result<_>>()?
+            } else {
+                elems
+            };
+            (record, value_t, elems)
+        }
+        _ => return Err(anyhow!("Unsupported result operation {op:?}")),
+    };
+
+    let null_label = label_t.const_null();
+    for value in values {
+        // Array elements are recorded without a label, following the array header.
+        let elem_label = match op {
+            ResultOpDef::Bool | ResultOpDef::Int | ResultOpDef::UInt | ResultOpDef::F64 => label,
+            _ => null_label,
+        };
+        emit_qir_call(
+            context,
+            record,
+            &[value_t, label_t.into()],
+            None,
+            &[value.into(), elem_label.into()],
+        )?;
+    }
+    args.outputs.finish(context.builder(), [])
+}
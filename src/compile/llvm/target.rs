@@ -0,0 +1,101 @@
+//! Target machine selection for the LLVM output.
+
+use hugr::llvm::inkwell::OptimizationLevel;
+use hugr::llvm::inkwell::module::Module;
+use hugr::llvm::inkwell::targets::{
+    CodeModel, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple,
+};
+
+use crate::cli::{OptimisationLevel, TargetArgs};
+
+impl TargetArgs {
+    /// Create the [TargetMachine] for the selected target.
+    ///
+    /// Defaults to the host triple, CPU and features when no target is given.
+    /// When cross-compiling, the CPU defaults to `generic`.
+    pub fn target_machine(&self, opt: OptimisationLevel) -> anyhow::Result<TargetMachine> {
+        Target::initialize_all(&InitializationConfig::default());
+
+        let (triple, cpu, features) = match &self.target {
+            Some(triple) => (
+                triple.clone(),
+                self.cpu.clone().unwrap_or_else(|| "generic".to_string()),
+                self.features.clone().unwrap_or_default(),
+            ),
+            None => (
+                TargetMachine::get_default_triple()
+                    .as_str()
+                    .to_string_lossy()
+                    .into_owned(),
+                self.cpu
+                    .clone()
+                    .unwrap_or_else(|| TargetMachine::get_host_cpu_name().to_string()),
+                self.features
+                    .clone()
+                    .unwrap_or_else(|| TargetMachine::get_host_cpu_features().to_string()),
+            ),
+        };
+
+        create_target_machine(&triple, &cpu, &features, opt)
+    }
+
+    /// Returns `true` if no target, CPU or features were selected.
+    pub fn is_default(&self) -> bool {
+        self.target.is_none() && self.cpu.is_none() && self.features.is_none()
+    }
+}
+
+/// Create a [TargetMachine] for the target triple a module already declares,
+/// with a generic CPU.
+///
+/// Returns `None` if the module has no target triple.
+pub fn module_target_machine(
+    module: &Module<'_>,
+    opt: OptimisationLevel,
+) -> anyhow::Result<Option<TargetMachine>> {
+    let triple = module.get_triple();
+    let triple = triple.as_str().to_string_lossy();
+    if triple.is_empty() {
+        return Ok(None);
+    }
+    Target::initialize_all(&InitializationConfig::default());
+    create_target_machine(&triple, "generic", "", opt).map(Some)
+}
+
+fn create_target_machine(
+    triple: &str,
+    cpu: &str,
+    features: &str,
+    opt: OptimisationLevel,
+) -> anyhow::Result<TargetMachine> {
+    let llvm_triple = TargetTriple::create(triple);
+    let target = Target::from_triple(&llvm_triple)
+        .map_err(|e| anyhow::anyhow!("Unsupported target {triple}: {e}"))?;
+    target
+        .create_target_machine(
+            &llvm_triple,
+            cpu,
+            features,
+            opt.into(),
+            RelocMode::PIC,
+            CodeModel::Default,
+        )
+        .ok_or_else(|| anyhow::anyhow!("Could not create a target machine for {triple} ({cpu})"))
+}
+
+/// Set the target triple and data layout of a module.
+pub fn set_target(module: &Module<'_>, machine: &TargetMachine) {
+    module.set_triple(&machine.get_triple());
+    module.set_data_layout(&machine.get_target_data().get_data_layout());
+}
+
+impl From<OptimisationLevel> for OptimizationLevel {
+    fn from(opt: OptimisationLevel) -> Self {
+        match opt {
+            OptimisationLevel::O0 => OptimizationLevel::None,
+            OptimisationLevel::O1 => OptimizationLevel::Less,
+            OptimisationLevel::O2 => OptimizationLevel::Default,
+            OptimisationLevel::O3 => OptimizationLevel::Aggressive,
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Structural differences between two HUGR packages.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use hugr::ops::NamedOp;
+use hugr::{Hugr, HugrView, Node};
+use itertools::{EitherOrBoth, Itertools};
+use serde::Serialize;
+
+use crate::cli::{DiffArgs, ReportFormat};
+use crate::compile::hugr::HugrStage;
+use crate::compile::hugr::compare::{self, NodeDifference, Precision};
+use crate::compile::hugr::functions::{self, FunctionInfo};
+
+impl DiffArgs {
+    /// Run the `diff` subcommand, printing the differences between the packages.
+    pub fn run(&self) -> anyhow::Result<()> {
+        let registry = self.extensions.registry()?;
+        let before = HugrStage::from_file(&self.before, &registry)?;
+        let after = HugrStage::from_file(&self.after, &registry)?;
+
+        let diff = PackageDiff::new(before.hugr(), after.hugr(), self.nodes);
+        match self.format {
+            ReportFormat::Text => print!("{}", diff.to_text()),
+            ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+        }
+        Ok(())
+    }
+}
+
+/// Differences between the functions of two HUGRs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackageDiff {
+    /// Functions only present in the second HUGR.
+    pub added: Vec<FunctionRef>,
+    /// Functions only present in the first HUGR.
+    pub removed: Vec<FunctionRef>,
+    /// Functions present in both HUGRs with some differences.
+    pub changed: Vec<FunctionDiff>,
+}
+
+/// A function in one of the compared HUGRs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FunctionRef {
+    /// The qualified function name.
+    pub name: String,
+    /// The function signature.
+    pub signature: String,
+}
+
+/// Differences between two functions with the same name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FunctionDiff {
+    /// The qualified function name.
+    pub name: String,
+    /// The signatures before and after, if they differ.
+    pub signature: Option<(String, String)>,
+    /// Change in the number of operations of each kind.
+    pub op_deltas: BTreeMap<String, i64>,
+    /// Node-level differences, if requested.
+    pub nodes: Vec<NodeDifference>,
+}
+
+impl PackageDiff {
+    /// Compare the functions in two HUGRs, matching them by qualified name.
+    ///
+    /// If `node_diff` is set, matched functions are also compared node by node.
+    pub fn new(before: &Hugr, after: &Hugr, node_diff: bool) -> Self {
+        let before_fns = functions_by_name(before);
+        let after_fns = functions_by_name(after);
+        let mut diff = PackageDiff {
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        };
+
+        for entry in before_fns
+            .iter()
+            .merge_join_by(&after_fns, |(a, _), (b, _)| a.cmp(b))
+        {
+            match entry {
+                EitherOrBoth::Left((_, fns)) => {
+                    diff.removed.extend(fns.iter().map(FunctionRef::new))
+                }
+                EitherOrBoth::Right((_, fns)) => {
+                    diff.added.extend(fns.iter().map(FunctionRef::new))
+                }
+                EitherOrBoth::Both((_, left_fns), (_, right_fns)) => {
+                    // Functions with duplicated names are paired in order.
+                    for pair in left_fns.iter().zip_longest(right_fns) {
+                        match pair {
+                            EitherOrBoth::Left(l) => diff.removed.push(FunctionRef::new(l)),
+                            EitherOrBoth::Right(r) => diff.added.push(FunctionRef::new(r)),
+                            EitherOrBoth::Both(l, r) => {
+                                let fn_diff = FunctionDiff::new(before, l, after, r, node_diff);
+                                if !fn_diff.is_empty() {
+                                    diff.changed.push(fn_diff);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        diff
+    }
+
+    /// Human-readable representation of the differences.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        if self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty() {
+            writeln!(text, "No differences found.").unwrap();
+            return text;
+        }
+        for f in &self.removed {
+            writeln!(text, "- {}: {}", f.name, f.signature).unwrap();
+        }
+        for f in &self.added {
+            writeln!(text, "+ {}: {}", f.name, f.signature).unwrap();
+        }
+        for f in &self.changed {
+            writeln!(text, "~ {}", f.name).unwrap();
+            if let Some((before, after)) = &f.signature {
+                writeln!(text, "    signature: {before} -> {after}").unwrap();
+            }
+            for (op, delta) in &f.op_deltas {
+                writeln!(text, "    {op}: {delta:+}").unwrap();
+            }
+            for node in &f.nodes {
+                writeln!(text, "    {node}").unwrap();
+            }
+        }
+        text
+    }
+}
+
+impl FunctionRef {
+    fn new(f: &FunctionInfo) -> Self {
+        Self {
+            name: f.qualified_name.clone(),
+            signature: f.signature.to_string(),
+        }
+    }
+}
+
+impl FunctionDiff {
+    fn new(
+        before: &Hugr,
+        left: &FunctionInfo,
+        after: &Hugr,
+        right: &FunctionInfo,
+        node_diff: bool,
+    ) -> Self {
+        let signature = (left.signature != right.signature)
+            .then(|| (left.signature.to_string(), right.signature.to_string()));
+
+        let mut op_deltas: BTreeMap<String, i64> = BTreeMap::new();
+        for (op, count) in op_counts(before, left.node) {
+            *op_deltas.entry(op).or_default() -= count;
+        }
+        for (op, count) in op_counts(after, right.node) {
+            *op_deltas.entry(op).or_default() += count;
+        }
+        op_deltas.retain(|_, delta| *delta != 0);
+
+        let nodes = if node_diff {
+            compare::compare_regions(
+                before,
+                left.node,
+                after,
+                right.node,
+                Precision::Labels,
+                None,
+            )
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            name: left.qualified_name.clone(),
+            signature,
+            op_deltas,
+            nodes,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.signature.is_none() && self.op_deltas.is_empty() && self.nodes.is_empty()
+    }
+}
+
+/// Group the functions of a HUGR by their qualified name.
+fn functions_by_name(hugr: &Hugr) -> BTreeMap<String, Vec<FunctionInfo>> {
+    let mut by_name: BTreeMap<String, Vec<FunctionInfo>> = BTreeMap::new();
+    for f in functions::functions(hugr) {
+        by_name.entry(f.qualified_name.clone()).or_default().push(f);
+    }
+    by_name
+}
+
+/// Count the operations of each kind directly inside a function.
+fn op_counts(hugr: &Hugr, func: Node) -> BTreeMap<String, i64> {
+    let mut counts = BTreeMap::new();
+    for node in hugr.descendants(func).skip(1) {
+        if functions::enclosing_function(hugr, node) == Some(func) {
+            *counts
+                .entry(hugr.get_optype(node).name().to_string())
+                .or_default() += 1;
+        }
+    }
+    counts
+}
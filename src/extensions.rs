@@ -0,0 +1,156 @@
+//! Extension definitions used when loading HUGRs.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::{fs, io};
+
+use derive_more::{Display, Error};
+use hugr::Extension;
+use hugr::extension::{ExtensionId, ExtensionRegistry};
+use itertools::Itertools;
+use semver::Version;
+
+use crate::cli::ExtensionArgs;
+
+/// Returns a registry with the standard extensions and the tket2 quantum,
+/// rotation, futures and result extensions.
+pub fn default_registry() -> ExtensionRegistry {
+    let mut registry = hugr::std_extensions::std_reg();
+    for ext in [
+        &tket2::extension::TKET2_EXTENSION,
+        &tket2::extension::rotation::ROTATION_EXTENSION,
+        &tket2_hseries::extension::futures::EXTENSION,
+        &tket2_hseries::extension::result::EXTENSION,
+    ] {
+        registry.register_updated(Arc::clone(ext));
+    }
+    registry
+}
+
+impl ExtensionArgs {
+    /// Returns the extension registry containing the built-in extensions and
+    /// any extension definitions passed on the command line.
+    ///
+    /// A definition passed on the command line replaces a built-in one with
+    /// an older version. Differing definitions with the same name and version
+    /// are reported as a conflict.
+    pub fn registry(&self) -> anyhow::Result<ExtensionRegistry> {
+        let mut loaded: HashMap<ExtensionId, (Arc<Extension>, String)> = HashMap::new();
+        for path in self.extension_files()? {
+            let ext = load_extension(&path)?;
+            let source = path.display().to_string();
+
+            if let Some((previous_ext, previous)) = loaded.get(ext.name()) {
+                let version = previous_ext.version();
+                if version == ext.version() {
+                    return Err(ExtensionLoadError::Conflict {
+                        name: ext.name().clone(),
+                        version: version.clone(),
+                        first: previous.clone(),
+                        second: source,
+                    }
+                    .into());
+                }
+                log::warn!(
+                    "Extension {} is defined with versions {version} in {previous} and {} in {source}. Using the newest one.",
+                    ext.name(),
+                    ext.version()
+                );
+                if version > ext.version() {
+                    continue;
+                }
+            }
+
+            log::debug!(
+                "Loaded extension {} {} from {source}",
+                ext.name(),
+                ext.version()
+            );
+            loaded.insert(ext.name().clone(), (ext, source));
+        }
+
+        let mut extensions = Vec::new();
+        for builtin in default_registry().iter() {
+            let Some((ext, source)) = loaded.get(builtin.name()) else {
+                extensions.push(Arc::clone(builtin));
+                continue;
+            };
+            if ext == builtin {
+                log::debug!(
+                    "Extension {} from {source} is identical to the built-in definition",
+                    ext.name()
+                );
+            } else if ext.version() == builtin.version() {
+                return Err(ExtensionLoadError::Conflict {
+                    name: ext.name().clone(),
+                    version: ext.version().clone(),
+                    first: "the built-in extensions".to_string(),
+                    second: source.clone(),
+                }
+                .into());
+            } else {
+                log::warn!(
+                    "Extension {} is defined with versions {} in the built-in extensions and {} in {source}. Using the newest one.",
+                    ext.name(),
+                    builtin.version(),
+                    ext.version()
+                );
+                if ext.version() > builtin.version() {
+                    continue;
+                }
+            }
+            loaded.remove(builtin.name());
+            extensions.push(Arc::clone(builtin));
+        }
+        extensions.extend(loaded.into_values().map(|(ext, _)| ext));
+        Ok(ExtensionRegistry::new(extensions))
+    }
+
+    /// Returns the paths of all the extension definitions to load, including
+    /// the `.json` files in the extension directories.
+    fn extension_files(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut files = self.extension.clone();
+        for dir in &self.extension_dir {
+            let entries = fs::read_dir(dir).map_err(|e| {
+                anyhow::anyhow!("Cannot read extension directory {}. {e}", dir.display())
+            })?;
+            let dir_files: Vec<PathBuf> = entries
+                .map_ok(|entry| entry.path())
+                .filter_ok(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect::<io::Result<_>>()?;
+            files.extend(dir_files.into_iter().sorted());
+        }
+        Ok(files)
+    }
+}
+
+/// Load a serialized extension definition.
+fn load_extension(path: &Path) -> anyhow::Result<Arc<Extension>> {
+    let file = fs::File::open(path)?;
+    let reader = io::BufReader::new(file);
+    let ext: Extension = serde_json::from_reader(reader).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to load extension definition {}. {e}",
+            path.display()
+        )
+    })?;
+    Ok(Arc::new(ext))
+}
+
+/// Error loading the extension definitions.
+#[derive(Debug, Display, Error)]
+pub enum ExtensionLoadError {
+    /// Two differing extension definitions share the same name and version.
+    #[display("Extension {name} version {version} is defined both in {first} and {second}.")]
+    Conflict {
+        /// The extension name.
+        name: ExtensionId,
+        /// The extension version.
+        version: Version,
+        /// Where the first definition was found.
+        first: String,
+        /// Where the conflicting definition was found.
+        second: String,
+    },
+}
@@ -0,0 +1,45 @@
+//! Reports about the contents of a program.
+
+use crate::cli::{InspectArgs, ReportFormat};
+use crate::compile::hugr::passes::HugrPass;
+
+pub mod package;
+pub mod resources;
+
+use package::PackageReport;
+use resources::ResourceReport;
+
+impl InspectArgs {
+    /// Run the `inspect` subcommand, printing the requested reports.
+    pub fn run(&self) -> anyhow::Result<()> {
+        let mut stage = self
+            .input
+            .load_hugr(&self.guppy_version, &self.extensions)?;
+
+        if self.resources {
+            // Resources are counted on the monomorphized program.
+            HugrPass::Monomorphize.run(stage.hugr_mut(), None)?;
+            let entrypoint = stage.entrypoint(self.entrypoint.as_deref())?;
+            let report = ResourceReport::new(stage.hugr(), entrypoint);
+            self.print(&report, ResourceReport::to_text)?;
+        } else {
+            let report = PackageReport::new(&stage.pkg, &self.extensions.registry()?);
+            self.print(&report, PackageReport::to_text)?;
+        }
+
+        Ok(())
+    }
+
+    /// Print a report in the requested format.
+    fn print<T: serde::Serialize>(
+        &self,
+        report: &T,
+        to_text: impl Fn(&T) -> String,
+    ) -> anyhow::Result<()> {
+        match self.format {
+            ReportFormat::Text => print!("{}", to_text(report)),
+            ReportFormat::Json => println!("{}", serde_json::to_string_pretty(report)?),
+        }
+        Ok(())
+    }
+}
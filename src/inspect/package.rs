@@ -0,0 +1,236 @@
+//! Summary of the modules, functions and extensions in a HUGR package.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use hugr::extension::{ExtensionId, ExtensionRegistry};
+use hugr::ops::OpType;
+use hugr::package::Package;
+use hugr::types::{Type, TypeArg, TypeEnum};
+use hugr::{HugrView, NodeIndex};
+use serde::Serialize;
+
+use crate::compile::hugr::functions;
+
+/// Summary of a HUGR package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackageReport {
+    /// The modules in the package.
+    pub modules: Vec<ModuleReport>,
+}
+
+/// Summary of a module in a HUGR package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModuleReport {
+    /// Position of the module in the package.
+    pub index: usize,
+    /// The module name, if it has one.
+    pub name: Option<String>,
+    /// Number of nodes in the module.
+    pub nodes: usize,
+    /// Number of edges in the module.
+    pub edges: usize,
+    /// Extensions used by operations or values in the module.
+    pub extensions: Vec<ExtensionUsage>,
+    /// Function definitions and declarations.
+    pub functions: Vec<FunctionSummary>,
+}
+
+/// An extension used by a module.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExtensionUsage {
+    /// The extension name.
+    pub name: String,
+    /// The version of the extension, if it is known.
+    pub version: Option<String>,
+    /// Whether the version is the one declared by the package, rather than
+    /// the one in the local registry.
+    pub declared: bool,
+}
+
+/// A function definition or declaration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FunctionSummary {
+    /// The qualified function name.
+    pub name: String,
+    /// The index of the function node.
+    pub node: usize,
+    /// The function signature.
+    pub signature: String,
+    /// Whether the function is a declaration without a body.
+    pub declaration: bool,
+    /// Functions called or loaded by this function.
+    pub calls: Vec<String>,
+}
+
+impl PackageReport {
+    /// Summarise a package.
+    ///
+    /// The extension versions are taken from the extensions declared by the
+    /// package, or from `registry` for the extensions it does not declare.
+    pub fn new(pkg: &Package, registry: &ExtensionRegistry) -> Self {
+        let modules = pkg
+            .modules
+            .iter()
+            .enumerate()
+            .map(|(index, hugr)| ModuleReport::new(index, hugr, pkg, registry))
+            .collect();
+        Self { modules }
+    }
+
+    /// Human-readable representation of the report.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for module in &self.modules {
+            module.write_text(&mut text);
+        }
+        text
+    }
+}
+
+impl ModuleReport {
+    fn new(
+        index: usize,
+        hugr: &impl HugrView,
+        pkg: &Package,
+        registry: &ExtensionRegistry,
+    ) -> Self {
+        let funcs = functions::functions(hugr);
+        let graph = functions::call_graph(hugr);
+        let name_of = |node| {
+            funcs
+                .iter()
+                .find(|f| f.node == node)
+                .map_or_else(|| format!("{node}"), |f| f.qualified_name.clone())
+        };
+
+        let functions = funcs
+            .iter()
+            .map(|f| FunctionSummary {
+                name: f.qualified_name.clone(),
+                node: f.node.index(),
+                signature: f.signature.to_string(),
+                declaration: f.is_declaration,
+                calls: graph
+                    .get(&f.node)
+                    .into_iter()
+                    .flatten()
+                    .map(|&callee| name_of(callee))
+                    .collect(),
+            })
+            .collect();
+
+        let extensions = used_extensions(hugr)
+            .into_iter()
+            .map(|ext| {
+                let declared = pkg.extensions.iter().find(|e| *e.name() == ext);
+                let version = declared
+                    .or_else(|| registry.get(&ext))
+                    .map(|e| e.version().to_string());
+                ExtensionUsage {
+                    name: ext.to_string(),
+                    version,
+                    declared: declared.is_some(),
+                }
+            })
+            .collect();
+
+        Self {
+            index,
+            name: functions::module_name(hugr).map(ToString::to_string),
+            nodes: hugr.node_count(),
+            edges: hugr.edge_count(),
+            extensions,
+            functions,
+        }
+    }
+
+    fn write_text(&self, text: &mut String) {
+        let name = self.name.as_deref().unwrap_or("<unnamed>");
+        writeln!(text, "Module {} {name}", self.index).unwrap();
+        writeln!(text, "  nodes: {}, edges: {}", self.nodes, self.edges).unwrap();
+        writeln!(text, "  extensions:").unwrap();
+        for ext in &self.extensions {
+            let version = match (&ext.version, ext.declared) {
+                (Some(version), true) => version.clone(),
+                (Some(version), false) => format!("{version} in the local registry"),
+                (None, _) => "unknown version".to_string(),
+            };
+            writeln!(text, "    {} ({version})", ext.name).unwrap();
+        }
+        writeln!(text, "  functions:").unwrap();
+        for f in &self.functions {
+            let kind = if f.declaration { "decl" } else { "defn" };
+            writeln!(
+                text,
+                "    [{kind}] {} (node {}): {}",
+                f.name, f.node, f.signature
+            )
+            .unwrap();
+            if !f.calls.is_empty() {
+                writeln!(text, "      calls: {}", f.calls.join(", ")).unwrap();
+            }
+        }
+    }
+}
+
+/// Returns the extensions defining operations, or the types of values, in the
+/// HUGR, including types nested in other types or passed as type arguments.
+fn used_extensions(hugr: &impl HugrView) -> BTreeSet<ExtensionId> {
+    let mut extensions = BTreeSet::new();
+    for node in hugr.nodes() {
+        if let OpType::ExtensionOp(ext_op) = hugr.get_optype(node) {
+            extensions.insert(ext_op.def().extension_id().clone());
+            for arg in ext_op.args() {
+                arg_extensions(arg, &mut extensions);
+            }
+        }
+        for (_, ty) in hugr.out_value_types(node) {
+            type_extensions(&ty, &mut extensions);
+        }
+    }
+    extensions
+}
+
+/// Collect the extensions defining a type and the types nested in it.
+fn type_extensions(ty: &Type, extensions: &mut BTreeSet<ExtensionId>) {
+    match ty.as_type_enum() {
+        TypeEnum::Extension(custom) => {
+            extensions.insert(custom.parent_extension().clone());
+            for arg in custom.args() {
+                arg_extensions(arg, extensions);
+            }
+        }
+        TypeEnum::Sum(sum) => {
+            for ty in (0..sum.num_variants())
+                .filter_map(|tag| sum.get_variant(tag))
+                .flat_map(|row| row.iter())
+            {
+                if let Ok(ty) = Type::try_from(ty.clone()) {
+                    type_extensions(&ty, extensions);
+                }
+            }
+        }
+        TypeEnum::Function(func) => {
+            for ty in func.input().iter().chain(func.output().iter()) {
+                if let Ok(ty) = Type::try_from(ty.clone()) {
+                    type_extensions(&ty, extensions);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collect the extensions defining the types in a type argument.
+fn arg_extensions(arg: &TypeArg, extensions: &mut BTreeSet<ExtensionId>) {
+    match arg {
+        TypeArg::Type { ty } => type_extensions(ty, extensions),
+        TypeArg::Sequence { elems } => {
+            for elem in elems {
+                arg_extensions(elem, extensions);
+            }
+        }
+        _ => {}
+    }
+}
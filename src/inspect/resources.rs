@@ -0,0 +1,426 @@
+//! Quantum resource usage of the functions in a HUGR.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+use hugr::extension::prelude::qb_t;
+use hugr::ops::OpType;
+use hugr::{HugrView, IncomingPort, Node, NodeIndex, OutgoingPort, PortIndex};
+use serde::Serialize;
+
+use crate::compile::hugr::functions;
+
+/// Extension defining the quantum operations.
+const QUANTUM_EXTENSION: &str = "tket2.quantum";
+/// Quantum operations allocating a new qubit.
+const ALLOC_OPS: &[&str] = &["QAlloc", "TryQAlloc"];
+/// Quantum operations deallocating a qubit.
+const FREE_OPS: &[&str] = &["QFree"];
+/// Quantum operations measuring a qubit.
+pub(crate) const MEASURE_OPS: &[&str] = &["Measure", "MeasureFree"];
+
+/// Quantum resources used by a function.
+///
+/// Operations are counted once per occurrence in the HUGR, so the counts are
+/// static: an operation in a loop body or in a function called several times
+/// is only counted once.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Resources {
+    /// Number of qubits received as inputs, plus the number of allocation sites.
+    ///
+    /// Allocations repeated by a loop are only counted once, so this is a
+    /// lower bound on the number of qubits allocated when the program runs.
+    pub static_qubits: usize,
+    /// Number of qubit allocation operations.
+    pub allocations: usize,
+    /// Number of gates of each kind.
+    pub gates: BTreeMap<String, usize>,
+    /// Number of measurements.
+    pub measurements: usize,
+    /// Maximum number of qubits alive at the same time, including those used
+    /// by called functions.
+    pub max_live_qubits: usize,
+    /// Whether a measurement result is used to decide the control flow.
+    pub classical_control: bool,
+}
+
+/// Resources used by a named function.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FunctionResources {
+    /// The qualified function name.
+    pub name: String,
+    /// The index of the function node.
+    pub node: usize,
+    /// The resources used.
+    #[serde(flatten)]
+    pub resources: Resources,
+}
+
+/// Quantum resources used by each function in a HUGR.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResourceReport {
+    /// Resources used by the operations directly inside each function definition.
+    pub functions: Vec<FunctionResources>,
+    /// Total resources used by the entrypoint and all the functions it calls.
+    pub entrypoint: Option<FunctionResources>,
+}
+
+impl ResourceReport {
+    /// Compute the resources used by every function in the HUGR.
+    ///
+    /// The HUGR should be monomorphized first.
+    pub fn new(hugr: &impl HugrView, entrypoint: Option<Node>) -> Self {
+        let funcs = functions::functions(hugr)
+            .into_iter()
+            .filter(|f| !f.is_declaration)
+            .collect::<Vec<_>>();
+        let mut local: HashMap<Node, Resources> = funcs
+            .iter()
+            .map(|f| (f.node, Resources::default()))
+            .collect();
+
+        for node in hugr.nodes() {
+            let Some(resources) = functions::enclosing_function(hugr, node)
+                .filter(|&f| f != node)
+                .and_then(|f| local.get_mut(&f))
+            else {
+                continue;
+            };
+            let Some(op_name) = quantum_op_name(hugr.get_optype(node)) else {
+                continue;
+            };
+            if ALLOC_OPS.contains(&op_name.as_str()) {
+                resources.allocations += 1;
+            } else if MEASURE_OPS.contains(&op_name.as_str()) {
+                resources.measurements += 1;
+                resources.classical_control |= drives_control_flow(hugr, node);
+            } else if !FREE_OPS.contains(&op_name.as_str()) {
+                *resources.gates.entry(op_name).or_default() += 1;
+            }
+        }
+
+        let mut liveness = Liveness::new(hugr);
+        for f in &funcs {
+            let resources = local.get_mut(&f.node).unwrap();
+            resources.static_qubits = qubit_inputs(f) + resources.allocations;
+            resources.max_live_qubits = liveness.function_peak(f.node);
+        }
+
+        let entrypoint = entrypoint.map(|entry| {
+            let graph = functions::call_graph(hugr);
+            let mut total = Resources::default();
+            for f in functions::reachable(&graph, entry) {
+                let Some(resources) = local.get(&f) else {
+                    continue;
+                };
+                total.allocations += resources.allocations;
+                total.measurements += resources.measurements;
+                total.classical_control |= resources.classical_control;
+                for (gate, count) in &resources.gates {
+                    *total.gates.entry(gate.clone()).or_default() += count;
+                }
+            }
+            let entry_info = funcs.iter().find(|f| f.node == entry);
+            total.static_qubits = entry_info.map_or(0, qubit_inputs) + total.allocations;
+            total.max_live_qubits = local.get(&entry).map_or(0, |r| r.max_live_qubits);
+            FunctionResources {
+                name: entry_info.map_or_else(|| entry.to_string(), |f| f.qualified_name.clone()),
+                node: entry.index(),
+                resources: total,
+            }
+        });
+
+        let functions = funcs
+            .into_iter()
+            .map(|f| FunctionResources {
+                resources: local.remove(&f.node).unwrap_or_default(),
+                name: f.qualified_name,
+                node: f.node.index(),
+            })
+            .collect();
+
+        Self {
+            functions,
+            entrypoint,
+        }
+    }
+
+    /// Human-readable representation of the report.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for f in &self.functions {
+            f.write_text(&mut text);
+        }
+        if let Some(entry) = &self.entrypoint {
+            writeln!(text, "Entrypoint call graph:").unwrap();
+            entry.write_text(&mut text);
+        }
+        text
+    }
+}
+
+impl FunctionResources {
+    fn write_text(&self, text: &mut String) {
+        let r = &self.resources;
+        writeln!(text, "{} (node {})", self.name, self.node).unwrap();
+        writeln!(text, "  static qubits:     {}", r.static_qubits).unwrap();
+        writeln!(text, "  allocations:       {}", r.allocations).unwrap();
+        writeln!(text, "  measurements:      {}", r.measurements).unwrap();
+        writeln!(text, "  max live qubits:   {}", r.max_live_qubits).unwrap();
+        writeln!(text, "  classical control: {}", r.classical_control).unwrap();
+        if !r.gates.is_empty() {
+            writeln!(text, "  gates:").unwrap();
+            for (gate, count) in &r.gates {
+                writeln!(text, "    {gate}: {count}").unwrap();
+            }
+        }
+    }
+}
+
+/// Returns the name of a quantum operation, if the op is one.
+pub(crate) fn quantum_op_name(op: &OpType) -> Option<String> {
+    let OpType::ExtensionOp(ext_op) = op else {
+        return None;
+    };
+    let def = ext_op.def();
+    (def.extension_id().to_string() == QUANTUM_EXTENSION).then(|| def.name().to_string())
+}
+
+/// Number of qubit inputs of a function.
+fn qubit_inputs(f: &functions::FunctionInfo) -> usize {
+    f.signature
+        .body()
+        .input_types()
+        .iter()
+        .filter(|ty| **ty == qb_t())
+        .count()
+}
+
+/// Returns `true` if the classical outputs of a measurement flow into the
+/// predicate of a conditional, loop or CFG block.
+///
+/// Values are followed into nested regions, into called functions and back
+/// out of them through the calls' outputs.
+fn drives_control_flow(hugr: &impl HugrView, measurement: Node) -> bool {
+    let mut queue: VecDeque<(Node, IncomingPort)> = hugr
+        .out_value_types(measurement)
+        .filter(|(_, ty)| *ty != qb_t())
+        .flat_map(|(port, _)| hugr.linked_inputs(measurement, port))
+        .collect();
+    let mut visited = HashSet::new();
+
+    while let Some((node, port)) = queue.pop_front() {
+        if !visited.insert((node, port)) {
+            continue;
+        }
+        let is_predicate = port.index() == 0;
+        match hugr.get_optype(node) {
+            OpType::Conditional(_) if is_predicate => return true,
+            // Values leaving a region continue from the outputs of its parent.
+            OpType::Output(_) => {
+                let Some(parent) = hugr.get_parent(node) else {
+                    continue;
+                };
+                match hugr.get_optype(parent) {
+                    OpType::TailLoop(_) | OpType::DataflowBlock(_) if is_predicate => {
+                        return true;
+                    }
+                    OpType::DFG(_) | OpType::TailLoop(_) => {
+                        queue.extend(hugr.all_linked_inputs(parent));
+                    }
+                    OpType::Case(_) => {
+                        if let Some(conditional) = hugr.get_parent(parent) {
+                            queue.extend(hugr.all_linked_inputs(conditional));
+                        }
+                    }
+                    // Block outputs continue into the successor blocks, or out
+                    // of the CFG from the exit block.
+                    OpType::DataflowBlock(_) => {
+                        for succ in hugr.output_neighbours(parent) {
+                            match hugr.children(succ).next() {
+                                Some(input) => queue.extend(hugr.all_linked_inputs(input)),
+                                None => {
+                                    if let Some(cfg) = hugr.get_parent(succ) {
+                                        queue.extend(hugr.all_linked_inputs(cfg));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    // Returned values continue from the outputs of the calls.
+                    OpType::FuncDefn(_) => {
+                        for (call, _) in hugr.all_linked_inputs(parent) {
+                            queue.extend(hugr.linked_inputs(call, port.index()));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => match region_inputs(hugr, node, port) {
+                Some(inputs) => {
+                    for (input, out_port) in inputs {
+                        queue.extend(hugr.linked_inputs(input, out_port));
+                    }
+                }
+                None => queue.extend(hugr.all_linked_inputs(node)),
+            },
+        }
+    }
+    false
+}
+
+/// Returns the `Input` node ports receiving a value passed to a container
+/// node or call, or `None` for other nodes and calls to declarations.
+fn region_inputs(
+    hugr: &impl HugrView,
+    node: Node,
+    port: IncomingPort,
+) -> Option<Vec<(Node, OutgoingPort)>> {
+    let input_of = |region: Node| hugr.children(region).next();
+    let inputs = match hugr.get_optype(node) {
+        OpType::DFG(_) | OpType::TailLoop(_) => vec![(input_of(node)?, port.index())],
+        OpType::CFG(_) => vec![(input_of(input_of(node)?)?, port.index())],
+        OpType::Call(_) => vec![(input_of(hugr.static_source(node)?)?, port.index())],
+        // The other inputs of a conditional follow the variant values in each case.
+        OpType::Conditional(_) => {
+            let n_inputs = hugr.in_value_types(node).count();
+            hugr.children(node)
+                .filter_map(|case| {
+                    let input = input_of(case)?;
+                    let n_case_inputs = hugr.out_value_types(input).count();
+                    Some((input, (n_case_inputs + port.index()).checked_sub(n_inputs)?))
+                })
+                .collect()
+        }
+        _ => return None,
+    };
+    Some(
+        inputs
+            .into_iter()
+            .map(|(input, index)| (input, OutgoingPort::from(index)))
+            .collect(),
+    )
+}
+
+/// Computes the maximum number of simultaneously live qubits in each region.
+struct Liveness<'h, H> {
+    hugr: &'h H,
+    /// Peak of each function, once computed.
+    functions: HashMap<Node, usize>,
+    /// Functions currently being computed, to cut recursion.
+    in_progress: HashSet<Node>,
+}
+
+impl<'h, H: HugrView> Liveness<'h, H> {
+    fn new(hugr: &'h H) -> Self {
+        Self {
+            hugr,
+            functions: HashMap::new(),
+            in_progress: HashSet::new(),
+        }
+    }
+
+    /// Peak number of live qubits during a call to a function.
+    fn function_peak(&mut self, func: Node) -> usize {
+        if let Some(&peak) = self.functions.get(&func) {
+            return peak;
+        }
+        if !self.in_progress.insert(func) {
+            // Recursive calls are not expanded.
+            return 0;
+        }
+        let peak = match self.hugr.get_optype(func) {
+            OpType::FuncDefn(_) => self.region_peak(func),
+            _ => 0,
+        };
+        self.in_progress.remove(&func);
+        self.functions.insert(func, peak);
+        peak
+    }
+
+    /// Peak number of live qubits inside a node, including its inputs.
+    fn node_peak(&mut self, node: Node) -> usize {
+        let hugr = self.hugr;
+        match hugr.get_optype(node) {
+            OpType::DFG(_) | OpType::TailLoop(_) | OpType::DataflowBlock(_) | OpType::Case(_) => {
+                self.region_peak(node)
+            }
+            OpType::Conditional(_) | OpType::CFG(_) => hugr
+                .children(node)
+                .map(|child| self.node_peak(child))
+                .max()
+                .unwrap_or(0),
+            OpType::Call(_) => hugr
+                .static_source(node)
+                .map_or(0, |func| self.function_peak(func)),
+            _ => 0,
+        }
+    }
+
+    /// Peak number of live qubits in a dataflow region.
+    fn region_peak(&mut self, parent: Node) -> usize {
+        let hugr = self.hugr;
+        let order = topological_children(hugr, parent);
+        let position: HashMap<Node, usize> =
+            order.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+
+        // Qubit wires between the children, as intervals of positions.
+        let mut wires = Vec::new();
+        for (start, &node) in order.iter().enumerate() {
+            for (port, ty) in hugr.out_value_types(node) {
+                if ty != qb_t() {
+                    continue;
+                }
+                for (target, _) in hugr.linked_inputs(node, port) {
+                    if let Some(&end) = position.get(&target) {
+                        wires.push((start, end));
+                    }
+                }
+            }
+        }
+
+        let mut peak = 0;
+        for (i, &node) in order.iter().enumerate() {
+            let before = wires.iter().filter(|&&(s, e)| s < i && e >= i).count();
+            let after = wires.iter().filter(|&&(s, e)| s <= i && e > i).count();
+            let inputs = wires.iter().filter(|&&(_, e)| e == i).count();
+            let inner = self.node_peak(node).saturating_sub(inputs);
+            peak = peak.max(before.max(after) + inner);
+        }
+        peak
+    }
+}
+
+/// Returns the children of a dataflow region in topological order.
+fn topological_children(hugr: &impl HugrView, parent: Node) -> Vec<Node> {
+    let children = hugr.children(parent).collect::<Vec<_>>();
+    let mut in_degree: HashMap<Node, usize> = children
+        .iter()
+        .map(|&n| {
+            let preds = hugr
+                .input_neighbours(n)
+                .filter(|&p| hugr.get_parent(p) == Some(parent))
+                .count();
+            (n, preds)
+        })
+        .collect();
+
+    let mut queue: VecDeque<Node> = children
+        .iter()
+        .copied()
+        .filter(|n| in_degree[n] == 0)
+        .collect();
+    let mut order = Vec::with_capacity(children.len());
+    while let Some(node) = queue.pop_front() {
+        order.push(node);
+        for succ in hugr.output_neighbours(node) {
+            if let Some(degree) = in_degree.get_mut(&succ) {
+                *degree -= 1;
+                if *degree == 0 {
+                    queue.push_back(succ);
+                }
+            }
+        }
+    }
+    order
+}
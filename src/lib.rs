@@ -1,2 +1,7 @@
+pub mod check;
 pub mod cli;
 pub mod compile;
+pub mod diff;
+pub mod extensions;
+pub mod inspect;
+pub mod qir_check;
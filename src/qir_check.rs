@@ -0,0 +1,35 @@
+//! Conformance checks of LLVM modules against the QIR profiles.
+
+use hugr::llvm::inkwell::context::Context;
+
+use crate::cli::{QirCheckArgs, ReportFormat};
+use crate::compile::llvm::parse_module;
+
+impl QirCheckArgs {
+    /// Run the `qir-check` subcommand, printing the profile violations found.
+    ///
+    /// Returns an error if any violation was found.
+    pub fn run(&self) -> anyhow::Result<()> {
+        let context = Context::create();
+        let module = parse_module(&self.input, &context)?;
+        let violations = self.profile.check_module(&module);
+
+        match self.format {
+            ReportFormat::Text => {
+                for violation in &violations {
+                    println!("{violation}");
+                }
+            }
+            ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&violations)?),
+        }
+
+        if !violations.is_empty() {
+            anyhow::bail!(
+                "Found {} violations of the {} QIR profile",
+                violations.len(),
+                self.profile
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,6 @@
+; Defines `main` again.
+
+define i64 @main() {
+entry:
+  ret i64 0
+}
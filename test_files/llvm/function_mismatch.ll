@@ -0,0 +1,6 @@
+; Defines the helper with a different type.
+
+define i32 @helper(i32 %x) {
+entry:
+  ret i32 %x
+}
@@ -0,0 +1,11 @@
+; Uses the program's counter with a different type.
+
+@counter = external global i32
+
+define i64 @helper(i64 %x) {
+entry:
+  %c = load i32, i32* @counter
+  %w = zext i32 %c to i64
+  %y = add i64 %x, %w
+  ret i64 %y
+}
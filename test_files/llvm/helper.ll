@@ -0,0 +1,20 @@
+; Defines the helper used by `program.ll`, with local symbols of the same
+; names as the program's.
+
+@counter = external global i64
+@scale = internal global i64 3
+
+define i64 @helper(i64 %x) {
+entry:
+  %c = load i64, i64* @counter
+  %l = call i64 @local()
+  %y = add i64 %x, %c
+  %z = add i64 %y, %l
+  ret i64 %z
+}
+
+define internal i64 @local() {
+entry:
+  %s = load i64, i64* @scale
+  ret i64 %s
+}
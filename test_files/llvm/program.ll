@@ -0,0 +1,20 @@
+; A program calling a helper defined in a library.
+
+@counter = global i64 0
+@scale = internal global i64 2
+
+define i64 @main() {
+entry:
+  %x = call i64 @helper(i64 1)
+  %y = call i64 @local()
+  %z = add i64 %x, %y
+  ret i64 %z
+}
+
+define internal i64 @local() {
+entry:
+  %s = load i64, i64* @scale
+  ret i64 %s
+}
+
+declare i64 @helper(i64)
@@ -0,0 +1,52 @@
+//! The linearity lint on hand-edited HUGRs.
+
+mod common;
+
+use guppyc::check::{FindingKind, check_linearity};
+use guppyc::compile::hugr::HugrStage;
+use guppyc::extensions::default_registry;
+use hugr::builder::{Dataflow, DataflowSubContainer, HugrBuilder, ModuleBuilder};
+use hugr::extension::prelude::{UnpackTuple, bool_t, qb_t};
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::package::Package;
+use hugr::types::Signature;
+use hugr::{IncomingPort, NodeIndex};
+use tket2::Tk2Op;
+
+#[test]
+fn check_leaking_hugr() -> anyhow::Result<()> {
+    // A valid program, measuring one qubit and freeing another through a tuple.
+    let mut module = ModuleBuilder::new();
+    let mut main = module.define_function("main", Signature::new(vec![], vec![bool_t()]))?;
+    let q0 = main.add_dataflow_op(Tk2Op::QAlloc, [])?.out_wire(0);
+    let h = main.add_dataflow_op(Tk2Op::H, [q0])?;
+    let measure = main.add_dataflow_op(Tk2Op::MeasureFree, [h.out_wire(0)])?;
+    let q1 = main.add_dataflow_op(Tk2Op::QAlloc, [])?.out_wire(0);
+    let tuple = main.make_tuple([q1])?;
+    let unpack = main.add_dataflow_op(UnpackTuple::new(vec![qb_t()].into()), [tuple])?;
+    main.add_dataflow_op(Tk2Op::QFree, [unpack.out_wire(0)])?;
+    main.finish_with_outputs([measure.out_wire(0)])?;
+    let mut hugr = module.finish_hugr()?;
+
+    // Drop the measured qubit and the tuple holding the other one.
+    hugr.disconnect(measure.node(), IncomingPort::from(0));
+    hugr.disconnect(unpack.node(), IncomingPort::from(0));
+
+    let dir = common::temp_dir("check_leaking_hugr");
+    let path = dir.join("leak.hugr");
+    std::fs::write(&path, Package::new([hugr])?.to_json()?)?;
+
+    // The HUGR is invalid, so it must be loaded without validation.
+    let stage = HugrStage::from_file_unvalidated(&path, &default_registry())?;
+
+    let mut leaks = check_linearity(stage.hugr())
+        .into_iter()
+        .filter(|f| f.kind == FindingKind::QubitLeak)
+        .map(|f| f.node)
+        .collect::<Vec<_>>();
+    leaks.sort();
+    let mut expected = vec![h.node().index(), tuple.node().index()];
+    expected.sort();
+    assert_eq!(leaks, expected);
+    Ok(())
+}
@@ -0,0 +1,55 @@
+//! Helpers shared by the integration tests.
+//!
+//! Compiling the guppy programs in `test_files` requires `uv`.
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use guppyc::cli::CliArgs;
+use guppyc::compile::guppy::GuppyStage;
+use guppyc::compile::hugr::HugrStage;
+use guppyc::compile::llvm::LLVMStage;
+use guppyc::compile::{CompilationStage, GenericStage};
+use guppyc::extensions::default_registry;
+
+/// The programs in `test_files`.
+pub const TEST_FILES: &[&str] = &["even_odd.py", "planqc-1.py", "planqc-2.py", "planqc-3.py"];
+
+/// Path to a program in `test_files`.
+pub fn test_file(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("test_files")
+        .join(name)
+}
+
+/// A fresh directory for the outputs of a test.
+pub fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join("guppyc-tests").join(test);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("Failed to create the test directory");
+    dir
+}
+
+/// Compile a program in `test_files` into a HUGR.
+pub fn load_hugr(name: &str) -> anyhow::Result<HugrStage> {
+    GuppyStage::with_default_guppy(test_file(name)).compile_hugr(&default_registry(), true)
+}
+
+/// Parse the CLI arguments for compiling a program in `test_files`.
+pub fn cli_args(name: &str, args: &[&str]) -> CliArgs {
+    let path = test_file(name);
+    let path = path.to_str().expect("Test file paths are valid UTF-8");
+    CliArgs::parse_from(["guppyc", path].into_iter().chain(args.iter().copied()))
+}
+
+/// Compile a program in `test_files` into LLVM, with additional CLI arguments.
+///
+/// The textual IR is always computed, but no file is written.
+pub fn compile_llvm(name: &str, args: &[&str]) -> anyhow::Result<LLVMStage> {
+    let cli_args = cli_args(name, &[["--llvm", "out.ll"].as_slice(), args].concat());
+    match load_hugr(name)?.compile(&cli_args)? {
+        GenericStage::LLVM(llvm) => Ok(llvm),
+        other => anyhow::bail!("Expected the LLVM stage, got {}", other.stage()),
+    }
+}
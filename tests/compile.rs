@@ -0,0 +1,61 @@
+//! Compilation of the `test_files` programs to LLVM.
+
+mod common;
+
+use common::{TEST_FILES, compile_llvm};
+
+#[test]
+fn compile_test_files() -> anyhow::Result<()> {
+    for name in TEST_FILES {
+        let llvm = compile_llvm(name, &[]).map_err(|e| anyhow::anyhow!("{name}: {e}"))?;
+        let entrypoint = llvm
+            .entrypoint
+            .as_deref()
+            .expect("`main` is the entrypoint");
+        let text = llvm.module_text.expect("The LLVM text was requested");
+        assert!(text.contains(&format!("@{entrypoint}(")), "{name}:\n{text}");
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_quantum_ops() -> anyhow::Result<()> {
+    let expected: &[(&str, &[&str])] = &[
+        (
+            "planqc-1.py",
+            &[
+                "__quantum__qis__h__body",
+                "__quantum__qis__rz__body",
+                "__quantum__qis__m__body",
+            ],
+        ),
+        (
+            "planqc-2.py",
+            &[
+                "__quantum__qis__h__body",
+                "__quantum__qis__cnot__body",
+                "__quantum__qis__z__body",
+                "__quantum__qis__x__body",
+                "__quantum__qis__m__body",
+            ],
+        ),
+        (
+            "planqc-3.py",
+            &[
+                "__quantum__qis__t__body",
+                "__quantum__qis__t__adj",
+                "__quantum__rt__qubit_allocate",
+                "__quantum__rt__qubit_release",
+            ],
+        ),
+    ];
+    for (name, calls) in expected {
+        let text = compile_llvm(name, &["--opt", "0"])?
+            .module_text
+            .expect("The LLVM text was requested");
+        for call in *calls {
+            assert!(text.contains(call), "{name} does not call {call}:\n{text}");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! Debug information in the LLVM output.
+
+mod common;
+
+use common::compile_llvm;
+
+#[test]
+fn debug_info_locates_functions() -> anyhow::Result<()> {
+    let text = compile_llvm("even_odd.py", &["-g"])?
+        .module_text
+        .expect("The LLVM text was requested");
+    assert!(
+        text.contains("DICompileUnit(language: DW_LANG_Python"),
+        "{text}"
+    );
+    assert!(text.contains("\"Debug Info Version\""), "{text}");
+
+    // Each function is located at its `def` line.
+    for (name, line) in [("is_even", 5), ("is_odd", 12), ("main", 19)] {
+        let subprogram = text
+            .lines()
+            .find(|l| l.contains("!DISubprogram(") && l.contains(&format!("name: \"{name}\"")))
+            .unwrap_or_else(|| panic!("No DISubprogram for {name}:\n{text}"));
+        assert!(
+            subprogram.contains(&format!(" line: {line},")),
+            "{subprogram}"
+        );
+        assert!(
+            text.contains(&format!("!DILocation(line: {line}, column: 1,")),
+            "No DILocation at line {line}:\n{text}"
+        );
+    }
+
+    // Every instruction of the functions has a location.
+    let mut in_function = false;
+    for line in text.lines() {
+        if line.starts_with("define ") {
+            in_function = true;
+        } else if line == "}" {
+            in_function = false;
+        } else if in_function && line.starts_with("  ") {
+            assert!(
+                line.contains("!dbg "),
+                "Instruction without location: {line}"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Returns the line of the location attached to the first call to `callee`.
+fn call_line(text: &str, callee: &str) -> Option<u32> {
+    let instruction = text
+        .lines()
+        .find(|l| l.contains(" call ") && l.contains(&format!("@{callee}(")))?;
+    let (_, id) = instruction.rsplit_once("!dbg ")?;
+    let location = text
+        .lines()
+        .find(|l| l.starts_with(&format!("{id} = !DILocation(")))?;
+    let (_, rest) = location.split_once("line: ")?;
+    rest.split(',').next()?.parse().ok()
+}
+
+#[test]
+fn debug_info_locates_operations() -> anyhow::Result<()> {
+    let text = compile_llvm("planqc-1.py", &["-g"])?
+        .module_text
+        .expect("The LLVM text was requested");
+
+    // The quantum operations are located at the statement they come from.
+    for (callee, line) in [
+        ("__quantum__rt__qubit_allocate", 16),
+        ("__quantum__qis__h__body", 9),
+        ("__quantum__qis__rz__body", 10),
+        ("__quantum__qis__m__body", 18),
+    ] {
+        assert_eq!(call_line(&text, callee), Some(line), "{callee}:\n{text}");
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Structural differences between HUGR packages.
+
+mod common;
+
+use common::{TEST_FILES, load_hugr};
+use guppyc::diff::{FunctionDiff, FunctionRef, PackageDiff};
+use hugr::builder::{Dataflow, DataflowSubContainer, HugrBuilder, ModuleBuilder};
+use hugr::extension::prelude::bool_t;
+use hugr::ops::Value;
+use hugr::types::Signature;
+
+/// The diff of a function present in both packages.
+fn changed<'d>(diff: &'d PackageDiff, name: &str) -> &'d FunctionDiff {
+    diff.changed
+        .iter()
+        .find(|f| f.name == name)
+        .unwrap_or_else(|| panic!("{name} is not changed in {diff:?}"))
+}
+
+/// The names of the added or removed functions.
+fn names(functions: &[FunctionRef]) -> Vec<&str> {
+    functions.iter().map(|f| f.name.as_str()).collect()
+}
+
+#[test]
+fn diff_package_with_itself() -> anyhow::Result<()> {
+    for name in TEST_FILES {
+        let stage = load_hugr(name)?;
+        let diff = PackageDiff::new(stage.hugr(), stage.hugr(), true);
+        assert!(diff.added.is_empty(), "{name}: {diff:?}");
+        assert!(diff.removed.is_empty(), "{name}: {diff:?}");
+        assert!(diff.changed.is_empty(), "{name}: {diff:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn diff_different_programs() -> anyhow::Result<()> {
+    let before = load_hugr("planqc-1.py")?;
+    let after = load_hugr("planqc-2.py")?;
+    let diff = PackageDiff::new(before.hugr(), after.hugr(), true);
+
+    assert!(names(&diff.removed).contains(&"rx"), "{diff:?}");
+    assert!(!names(&diff.removed).contains(&"main"), "{diff:?}");
+    assert!(names(&diff.added).contains(&"teleport"), "{diff:?}");
+
+    // Both `main` functions return a measurement, the second one allocating
+    // two qubits instead of one.
+    let main = changed(&diff, "main");
+    assert_eq!(main.signature, None);
+    let allocations = main
+        .op_deltas
+        .iter()
+        .find(|(op, _)| op.ends_with("QAlloc"))
+        .map(|(_, delta)| *delta);
+    assert_eq!(allocations, Some(1), "{main:?}");
+    assert!(!main.nodes.is_empty());
+    Ok(())
+}
+
+#[test]
+fn diff_signatures_and_ops() -> anyhow::Result<()> {
+    let identity = Signature::new_endo(vec![bool_t()]);
+    let constant = Signature::new(vec![], vec![bool_t()]);
+
+    let mut module = ModuleBuilder::new();
+    let f = module.define_function("f", identity.clone())?;
+    let [b] = f.input_wires_arr();
+    f.finish_with_outputs([b])?;
+    module
+        .define_function("g", Signature::new(vec![], vec![]))?
+        .finish_with_outputs([])?;
+    let before = module.finish_hugr()?;
+
+    let mut module = ModuleBuilder::new();
+    let mut f = module.define_function("f", constant.clone())?;
+    let b = f.add_load_value(Value::true_val());
+    f.finish_with_outputs([b])?;
+    module
+        .define_function("h", Signature::new(vec![], vec![]))?
+        .finish_with_outputs([])?;
+    let after = module.finish_hugr()?;
+
+    let diff = PackageDiff::new(&before, &after, false);
+    assert_eq!(names(&diff.removed), ["g"]);
+    assert_eq!(names(&diff.added), ["h"]);
+    assert_eq!(diff.changed.len(), 1);
+
+    let f = changed(&diff, "f");
+    assert_eq!(
+        f.signature,
+        Some((identity.to_string(), constant.to_string()))
+    );
+    // A constant and its load were added.
+    assert_eq!(f.op_deltas.len(), 2, "{f:?}");
+    assert_eq!(f.op_deltas.get("LoadConstant"), Some(&1), "{f:?}");
+    assert!(f.op_deltas.values().all(|&delta| delta == 1), "{f:?}");
+    assert!(f.nodes.is_empty());
+    Ok(())
+}
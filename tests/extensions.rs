@@ -0,0 +1,92 @@
+//! Loading extension definitions passed on the command line.
+
+use std::fs;
+use std::path::PathBuf;
+
+use guppyc::cli::ExtensionArgs;
+
+/// Write the built-in tket2 quantum extension to a file.
+fn tket2_quantum_file(name: &str) -> anyhow::Result<PathBuf> {
+    edited_tket2_quantum_file(name, |_| {})
+}
+
+/// Write the built-in tket2 quantum extension to a file, after editing its
+/// serialized definition.
+fn edited_tket2_quantum_file(
+    name: &str,
+    edit: impl FnOnce(&mut serde_json::Value),
+) -> anyhow::Result<PathBuf> {
+    let dir = std::env::temp_dir().join("guppyc-tests").join(name);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("tket2.quantum.json");
+    let mut definition = serde_json::to_value(tket2::extension::TKET2_EXTENSION.as_ref())?;
+    edit(&mut definition);
+    fs::write(&path, serde_json::to_string(&definition)?)?;
+    Ok(path)
+}
+
+#[test]
+fn builtin_definition_is_not_a_conflict() -> anyhow::Result<()> {
+    let path = tket2_quantum_file("builtin_definition_is_not_a_conflict")?;
+    let args = ExtensionArgs {
+        extension: vec![path],
+        ..Default::default()
+    };
+    let registry = args.registry()?;
+    assert_eq!(
+        registry.get("tket2.quantum").map(|ext| ext.as_ref()),
+        Some(tket2::extension::TKET2_EXTENSION.as_ref())
+    );
+    Ok(())
+}
+
+#[test]
+fn duplicate_definitions_conflict() -> anyhow::Result<()> {
+    let path = tket2_quantum_file("duplicate_definitions_conflict")?;
+    let args = ExtensionArgs {
+        extension: vec![path.clone(), path],
+        ..Default::default()
+    };
+    let err = args.registry().unwrap_err();
+    assert!(err.to_string().contains("is defined both in"), "{err}");
+    Ok(())
+}
+
+#[test]
+fn builtin_definition_conflict() -> anyhow::Result<()> {
+    // Same name and version as the built-in extension, without the `H` operation.
+    let path = edited_tket2_quantum_file("builtin_definition_conflict", |definition| {
+        definition["operations"]
+            .as_object_mut()
+            .expect("Extensions serialize their operations as a map")
+            .remove("H");
+    })?;
+    let args = ExtensionArgs {
+        extension: vec![path],
+        ..Default::default()
+    };
+    let err = args.registry().unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("is defined both in the built-in extensions"),
+        "{err}"
+    );
+    Ok(())
+}
+
+#[test]
+fn newer_definition_replaces_builtin() -> anyhow::Result<()> {
+    let path = edited_tket2_quantum_file("newer_definition_replaces_builtin", |definition| {
+        definition["version"] = "99.0.0".into();
+    })?;
+    let args = ExtensionArgs {
+        extension: vec![path],
+        ..Default::default()
+    };
+    let registry = args.registry()?;
+    let ext = registry
+        .get("tket2.quantum")
+        .expect("tket2.quantum is loaded");
+    assert_eq!(ext.version().to_string(), "99.0.0");
+    Ok(())
+}
@@ -0,0 +1,31 @@
+//! Extraction of functions into their own packages.
+
+mod common;
+
+use common::{cli_args, load_hugr, temp_dir};
+use guppyc::compile::CompilationStage;
+use guppyc::compile::hugr::HugrStage;
+use guppyc::compile::hugr::functions;
+use guppyc::extensions::default_registry;
+
+#[test]
+fn extract_keeps_callees() -> anyhow::Result<()> {
+    let dir = temp_dir("extract_keeps_callees");
+    let dir_arg = dir.to_str().unwrap();
+    let args = cli_args(
+        "even_odd.py",
+        &["--extract", "is_even", "--extract-dir", dir_arg],
+    );
+    load_hugr("even_odd.py")?.store(&args)?;
+
+    let extracted = HugrStage::from_file(dir.join("is_even.hugr"), &default_registry())?;
+    let mut names = functions::functions(extracted.hugr())
+        .into_iter()
+        .map(|f| f.qualified_name)
+        .collect::<Vec<_>>();
+    names.sort();
+    // `is_even` calls `is_odd`, but nothing calls `main`.
+    assert_eq!(names, ["is_even", "is_odd"]);
+    assert_eq!(std::fs::read_dir(&dir)?.count(), 1);
+    Ok(())
+}
@@ -0,0 +1,91 @@
+//! Function references given on the command line.
+
+use guppyc::compile::hugr::HugrStage;
+use guppyc::compile::hugr::functions::{FunctionSpec, SignatureSpec};
+use hugr::builder::{Container, Dataflow, DataflowSubContainer, HugrBuilder, ModuleBuilder};
+use hugr::extension::prelude::{bool_t, qb_t};
+use hugr::ops::Value;
+use hugr::package::Package;
+use hugr::std_extensions::arithmetic::float_types::float64_type;
+use hugr::types::Signature;
+use hugr::{Hugr, HugrView, Node, NodeIndex};
+
+/// A module with a top-level `main`, and another `main` nested in `helper`.
+///
+/// Returns the module, the top-level `main` and the nested `main`.
+fn nested_mains() -> anyhow::Result<(Hugr, Node, Node)> {
+    let mut module = ModuleBuilder::new();
+    let mut main = module.define_function("main", Signature::new(vec![], vec![bool_t()]))?;
+    let b = main.add_load_value(Value::true_val());
+    let main = main.finish_with_outputs([b])?;
+
+    let mut helper = module.define_function("helper", Signature::new_endo(vec![bool_t()]))?;
+    let nested = helper.define_function("main", Signature::new_endo(vec![bool_t()]))?;
+    let [b] = nested.input_wires_arr();
+    let nested = nested.finish_with_outputs([b])?;
+    let [b] = helper.input_wires_arr();
+    helper.finish_with_outputs([b])?;
+
+    Ok((module.finish_hugr()?, main.node(), nested.node()))
+}
+
+#[test]
+fn unqualified_name_prefers_top_level() -> anyhow::Result<()> {
+    let (hugr, main, nested) = nested_mains()?;
+    let stage = HugrStage {
+        pkg: Package::new([hugr])?,
+    };
+    assert_eq!(stage.find_funcdef_node("main")?, main);
+    assert_eq!(stage.find_funcdef_node("helper.main")?, nested);
+    assert_eq!(stage.entrypoint(None)?, Some(main));
+    Ok(())
+}
+
+#[test]
+fn signature_selects_overload() -> anyhow::Result<()> {
+    let (hugr, main, nested) = nested_mains()?;
+    let stage = HugrStage {
+        pkg: Package::new([hugr])?,
+    };
+    assert_eq!(stage.find_funcdef_node("main: [] -> [bool]")?, main);
+    assert_eq!(stage.find_funcdef_node("main: [Bool] -> [ Bool ]")?, nested);
+    assert!(stage.find_funcdef_node("main: [int] -> [bool]").is_err());
+    assert_eq!(
+        stage.find_funcdef_node(&format!("@n{}", nested.index()))?,
+        nested
+    );
+    Ok(())
+}
+
+#[test]
+fn signature_format() -> anyhow::Result<()> {
+    let (hugr, main, nested) = nested_mains()?;
+    let signature = |node: Node| {
+        let func = hugr.get_optype(node).as_func_defn().unwrap();
+        SignatureSpec::of(&func.signature).to_string()
+    };
+    assert_eq!(signature(main), "[] -> [bool]");
+    assert_eq!(signature(nested), "[bool] -> [bool]");
+
+    let qubits = Signature::new(vec![qb_t(), float64_type()], vec![qb_t()]);
+    assert_eq!(
+        SignatureSpec::of(&qubits.into()).to_string(),
+        "[qubit, float] -> [qubit]"
+    );
+    Ok(())
+}
+
+#[test]
+fn parse_function_specs() {
+    let spec: FunctionSpec = "outer.inner@n42".parse().unwrap();
+    assert_eq!(spec.name.as_deref(), Some("outer.inner"));
+    assert_eq!(spec.node, Some(42));
+
+    let spec: FunctionSpec = "main: [int, [[]+[]]] -> []".parse().unwrap();
+    let signature = spec.signature.unwrap();
+    assert_eq!(signature.inputs, ["int", "[[]+[]]"]);
+    assert!(signature.outputs.is_empty());
+
+    assert!("main: bool -> bool".parse::<FunctionSpec>().is_err());
+    assert!("main@x".parse::<FunctionSpec>().is_err());
+}
@@ -0,0 +1,78 @@
+//! HUGR artifacts stored for the `test_files` programs.
+
+mod common;
+
+use std::path::Path;
+
+use common::{TEST_FILES, cli_args, load_hugr, temp_dir};
+use guppyc::compile::CompilationStage;
+use guppyc::compile::hugr::HugrStage;
+use guppyc::compile::hugr::functions;
+use guppyc::extensions::default_registry;
+
+/// The sorted names of the functions in a stored HUGR.
+fn stored_functions(path: &Path) -> anyhow::Result<Vec<String>> {
+    let stage = HugrStage::from_file(path, &default_registry())?;
+    let mut names = functions::functions(stage.hugr())
+        .into_iter()
+        .map(|f| f.qualified_name)
+        .collect::<Vec<_>>();
+    names.sort();
+    Ok(names)
+}
+
+#[test]
+fn hugr_after_passes() -> anyhow::Result<()> {
+    let dir = temp_dir("hugr_after_passes");
+    let hugr_out = dir.join("out.hugr");
+    let dump_dir = dir.join("dumps");
+    std::fs::create_dir(&dump_dir)?;
+
+    // Nothing reachable from `is_even` calls `main`, so the pass removes it.
+    let args = |after_passes: bool| {
+        let mut args = vec![
+            "--entrypoint",
+            "is_even",
+            "--hugr-passes",
+            "dead-funcs,normalize-dfgs",
+            "--dump-hugr-after",
+            "dead-funcs",
+            "--dump-dir",
+            dump_dir.to_str().unwrap(),
+            "--hugr",
+            hugr_out.to_str().unwrap(),
+        ];
+        if after_passes {
+            args.push("--hugr-after-passes");
+        }
+        cli_args("even_odd.py", &args)
+    };
+    let stage = load_hugr("even_odd.py")?;
+
+    stage.store(&args(false))?;
+    assert_eq!(stored_functions(&hugr_out)?, ["is_even", "is_odd", "main"]);
+    let dumps = std::fs::read_dir(&dump_dir)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    assert_eq!(dumps, ["00-dead-funcs.hugr"]);
+    assert_eq!(
+        stored_functions(&dump_dir.join("00-dead-funcs.hugr"))?,
+        ["is_even", "is_odd"]
+    );
+
+    stage.store(&args(true))?;
+    assert_eq!(stored_functions(&hugr_out)?, ["is_even", "is_odd"]);
+    Ok(())
+}
+
+#[test]
+fn roundtrip_check_test_files() -> anyhow::Result<()> {
+    for name in TEST_FILES {
+        let stage = load_hugr(name)?;
+        let args = cli_args(name, &["--roundtrip-check"]);
+        stage
+            .store(&args)
+            .map_err(|e| anyhow::anyhow!("{name}: {e}"))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+//! Package reports of the `inspect` command.
+
+mod common;
+
+use std::sync::Arc;
+
+use common::load_hugr;
+use guppyc::extensions::default_registry;
+use guppyc::inspect::package::{ExtensionUsage, ModuleReport, PackageReport};
+use hugr::Extension;
+use hugr::builder::{Dataflow, DataflowSubContainer, HugrBuilder, ModuleBuilder};
+use hugr::package::Package;
+use hugr::std_extensions::arithmetic::float_types::float64_type;
+use hugr::types::{Signature, Type};
+use tket2::Tk2Op;
+
+/// The usage of an extension in a module, if it is used.
+fn extension<'m>(module: &'m ModuleReport, name: &str) -> Option<&'m ExtensionUsage> {
+    module.extensions.iter().find(|ext| ext.name == name)
+}
+
+#[test]
+fn even_odd_call_graph() -> anyhow::Result<()> {
+    let stage = load_hugr("even_odd.py")?;
+    let report = PackageReport::new(&stage.pkg, &default_registry());
+    let [module] = &report.modules[..] else {
+        panic!("Expected a single module: {report:?}");
+    };
+
+    let calls = |name: &str| {
+        let f = module
+            .functions
+            .iter()
+            .find(|f| f.name == name)
+            .unwrap_or_else(|| panic!("No function {name}"));
+        assert!(!f.declaration);
+        let mut calls = f.calls.clone();
+        calls.sort();
+        calls
+    };
+    assert_eq!(calls("main"), ["is_even", "is_odd"]);
+    assert_eq!(calls("is_even"), ["is_odd"]);
+    assert_eq!(calls("is_odd"), ["is_even"]);
+    assert!(extension(module, "arithmetic.int").is_some());
+    assert!(module.nodes > 0 && module.edges > 0);
+    Ok(())
+}
+
+#[test]
+fn declared_extension_versions() -> anyhow::Result<()> {
+    let mut module = ModuleBuilder::new();
+    let mut main = module.define_function("main", Signature::new(vec![], vec![]))?;
+    let q = main.add_dataflow_op(Tk2Op::QAlloc, [])?.out_wire(0);
+    main.add_dataflow_op(Tk2Op::QFree, [q])?;
+    main.finish_with_outputs([])?;
+    let mut pkg = Package::new([module.finish_hugr()?])?;
+
+    // The package was built with a different version of the quantum extension.
+    let mut definition = serde_json::to_value(tket2::extension::TKET2_EXTENSION.as_ref())?;
+    definition["version"] = "99.0.0".into();
+    let declared: Extension = serde_json::from_value(definition)?;
+    pkg.extensions.register_updated(Arc::new(declared));
+
+    let report = PackageReport::new(&pkg, &default_registry());
+    let quantum = extension(&report.modules[0], "tket2.quantum").expect("The extension is used");
+    assert_eq!(quantum.version.as_deref(), Some("99.0.0"));
+    assert!(quantum.declared);
+    Ok(())
+}
+
+#[test]
+fn extensions_in_nested_types() -> anyhow::Result<()> {
+    // Floats only appear inside a tuple, and no float operation is used.
+    let tuple = Type::new_tuple(vec![float64_type()]);
+    let mut module = ModuleBuilder::new();
+    let main = module.define_function("main", Signature::new_endo(vec![tuple]))?;
+    let [t] = main.input_wires_arr();
+    main.finish_with_outputs([t])?;
+    let pkg = Package::new([module.finish_hugr()?])?;
+
+    let report = PackageReport::new(&pkg, &default_registry());
+    assert!(extension(&report.modules[0], "arithmetic.float.types").is_some());
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! Linking LLVM libraries into a module.
+
+mod common;
+
+use common::test_file;
+use guppyc::compile::llvm::link::{LinkError, link_libraries};
+use guppyc::compile::llvm::parse_module;
+use hugr::llvm::inkwell::context::Context;
+
+/// Link a library from `test_files/llvm` into the program there.
+fn link(context: &Context, library: &str) -> anyhow::Result<String> {
+    let module = parse_module(&test_file("llvm/program.ll"), context)?;
+    link_libraries(context, &module, &[test_file(&format!("llvm/{library}"))])?;
+    module
+        .verify()
+        .map_err(|e| anyhow::anyhow!("Invalid linked module: {e}"))?;
+    Ok(module.to_string())
+}
+
+#[test]
+fn link_helper() -> anyhow::Result<()> {
+    let context = Context::create();
+    let text = link(&context, "helper.ll")?;
+    assert!(text.contains("define i64 @helper(i64 %x)"), "{text}");
+    // The local symbols of the library are renamed instead of clashing.
+    assert_eq!(
+        text.matches("define internal i64 @local").count(),
+        2,
+        "{text}"
+    );
+    assert_eq!(text.matches("internal global i64").count(), 2, "{text}");
+    Ok(())
+}
+
+#[test]
+fn link_duplicate_symbol() {
+    let context = Context::create();
+    let err = link(&context, "duplicate.ll").expect_err("`main` is defined twice");
+    assert!(
+        matches!(
+            err.downcast_ref::<LinkError>(),
+            Some(LinkError::DuplicateSymbol { symbol, .. }) if symbol == "main"
+        ),
+        "{err}"
+    );
+}
+
+#[test]
+fn link_type_mismatch() {
+    for (library, mismatched) in [
+        ("function_mismatch.ll", "helper"),
+        ("global_mismatch.ll", "counter"),
+    ] {
+        let context = Context::create();
+        let err = link(&context, library).expect_err("The types do not match");
+        assert!(
+            matches!(
+                err.downcast_ref::<LinkError>(),
+                Some(LinkError::TypeMismatch { symbol, .. }) if symbol == mismatched
+            ),
+            "{library}: {err}"
+        );
+    }
+}
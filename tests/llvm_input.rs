@@ -0,0 +1,135 @@
+//! Re-compiling previously emitted LLVM IR and bitcode.
+
+mod common;
+
+use std::fs;
+
+use clap::Parser;
+use common::{compile_llvm, temp_dir};
+use guppyc::cli::CliArgs;
+use guppyc::compile::{CompilationStage, GenericStage};
+
+const TARGET: &str = "aarch64-unknown-linux-gnu";
+
+#[test]
+fn recompile_llvm_inputs() -> anyhow::Result<()> {
+    let dir = temp_dir("recompile_llvm_inputs");
+    let compiled = compile_llvm("planqc-1.py", &[])?;
+    let entrypoint = compiled.entrypoint.expect("`main` is the entrypoint");
+    fs::write(dir.join("in.ll"), compiled.module_text.as_ref().unwrap())?;
+    fs::write(dir.join("in.bc"), compiled.module_bitcode.as_slice())?;
+
+    for (flag, input) in [("--llvm-input", "in.ll"), ("--bitcode-input", "in.bc")] {
+        let (input, llvm_out, object_out) =
+            (dir.join(input), dir.join("out.ll"), dir.join("out.o"));
+        let args = CliArgs::parse_from([
+            "guppyc",
+            flag,
+            input.to_str().unwrap(),
+            "-o",
+            "0",
+            "--target",
+            TARGET,
+            "--entrypoint",
+            entrypoint.as_str(),
+            "--llvm",
+            llvm_out.to_str().unwrap(),
+            "--object",
+            object_out.to_str().unwrap(),
+        ]);
+        args.validate()?;
+        let GenericStage::LLVM(llvm) = args.init_stage()? else {
+            panic!("{flag} starts at the LLVM stage");
+        };
+        assert_eq!(llvm.entrypoint.as_deref(), Some(entrypoint.as_str()));
+        llvm.store(&args)?;
+
+        let text = fs::read_to_string(&llvm_out)?;
+        assert!(
+            text.contains(&format!("target triple = \"{TARGET}\"")),
+            "{flag}:\n{text}"
+        );
+        assert!(text.contains(&format!("@{entrypoint}(")), "{flag}:\n{text}");
+        let object = fs::read(&object_out)?;
+        assert_eq!(&object[..4], b"\x7fELF", "{flag}");
+    }
+    Ok(())
+}
+
+#[test]
+fn llvm_input_keeps_its_target() -> anyhow::Result<()> {
+    let dir = temp_dir("llvm_input_keeps_its_target");
+    let compiled = compile_llvm("planqc-1.py", &["--target", TARGET])?;
+    let input = dir.join("in.ll");
+    fs::write(&input, compiled.module_text.as_ref().unwrap())?;
+
+    let args = CliArgs::parse_from([
+        "guppyc",
+        "--llvm-input",
+        input.to_str().unwrap(),
+        "--llvm",
+        "out.ll",
+    ]);
+    let GenericStage::LLVM(llvm) = args.init_stage()? else {
+        panic!("--llvm-input starts at the LLVM stage");
+    };
+    let text = llvm.module_text.expect("The LLVM text was requested");
+    assert!(
+        text.contains(&format!("target triple = \"{TARGET}\"")),
+        "{text}"
+    );
+    Ok(())
+}
+
+#[test]
+fn llvm_input_rejects_hugr_outputs() -> anyhow::Result<()> {
+    let dir = temp_dir("llvm_input_rejects_hugr_outputs");
+    let input = dir.join("in.ll");
+    fs::write(
+        &input,
+        compile_llvm("planqc-1.py", &[])?.module_text.unwrap(),
+    )?;
+
+    for output in ["--hugr", "--mermaid", "--sexpr"] {
+        let args = CliArgs::parse_from([
+            "guppyc",
+            "--llvm-input",
+            input.to_str().unwrap(),
+            output,
+            "out",
+        ]);
+        let err = args.validate().expect_err("LLVM inputs have no HUGR");
+        assert!(
+            err.to_string().contains("Cannot produce HUGR artifacts"),
+            "{output}: {err}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn llvm_input_rejects_debug_info() -> anyhow::Result<()> {
+    let dir = temp_dir("llvm_input_rejects_debug_info");
+    let input = dir.join("in.ll");
+    fs::write(
+        &input,
+        compile_llvm("planqc-1.py", &[])?.module_text.unwrap(),
+    )?;
+
+    let args = CliArgs::parse_from([
+        "guppyc",
+        "--llvm-input",
+        input.to_str().unwrap(),
+        "-g",
+        "--llvm",
+        "out.ll",
+    ]);
+    let err = args
+        .validate()
+        .expect_err("LLVM inputs have no source locations");
+    assert!(
+        err.to_string().contains("Cannot emit debug information"),
+        "{err}"
+    );
+    Ok(())
+}
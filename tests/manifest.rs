@@ -0,0 +1,84 @@
+//! The symbol manifest and the exported entrypoint name.
+
+mod common;
+
+use std::fs;
+
+use clap::Parser;
+use common::{cli_args, load_hugr, temp_dir, test_file};
+use guppyc::cli::CliArgs;
+use guppyc::compile::{CompilationStage, GenericStage};
+use serde_json::Value;
+
+#[test]
+fn manifest_with_export_name() -> anyhow::Result<()> {
+    let dir = temp_dir("manifest_with_export_name");
+    let (llvm_out, manifest_out) = (dir.join("out.ll"), dir.join("manifest.json"));
+    let args = cli_args(
+        "even_odd.py",
+        &[
+            "--entrypoint",
+            "main",
+            "--export-name",
+            "guppy_main",
+            "--llvm",
+            llvm_out.to_str().unwrap(),
+            "--manifest",
+            manifest_out.to_str().unwrap(),
+        ],
+    );
+    let GenericStage::LLVM(llvm) = load_hugr("even_odd.py")?.compile(&args)? else {
+        panic!("The HUGR compiles to LLVM");
+    };
+    llvm.store(&args)?;
+    let text = llvm.module_text.expect("The LLVM text was requested");
+    assert_eq!(llvm.entrypoint.as_deref(), Some("guppy_main"));
+
+    let manifest: Value = serde_json::from_str(&fs::read_to_string(&manifest_out)?)?;
+    assert_eq!(manifest["entrypoint"], "guppy_main");
+    let functions = manifest["functions"]
+        .as_array()
+        .expect("A list of functions");
+    let mut names = Vec::new();
+    for function in functions {
+        let symbol = function["symbol"].as_str().expect("A symbol name");
+        let name = function["hugr"]["name"].as_str().expect("A HUGR function");
+        names.push(name);
+        assert!(text.contains(&format!("@{symbol}(")), "{symbol}:\n{text}");
+        assert!(function["llvm_type"].is_string(), "{function}");
+        assert_eq!(function["entrypoint"], name == "main", "{function}");
+        if name == "main" {
+            assert_eq!(symbol, "guppy_main");
+            assert_eq!(function["linkage"], "External");
+        }
+    }
+    names.sort();
+    assert_eq!(names, ["is_even", "is_odd", "main"]);
+    Ok(())
+}
+
+#[test]
+fn export_name_clash() {
+    // `program.ll` defines the global `counter` and declares the function `helper`.
+    let input = test_file("llvm/program.ll");
+    for export_name in ["counter", "helper"] {
+        let args = CliArgs::parse_from([
+            "guppyc",
+            "--llvm-input",
+            input.to_str().unwrap(),
+            "--entrypoint",
+            "main",
+            "--export-name",
+            export_name,
+            "--llvm",
+            "out.ll",
+        ]);
+        let err = args
+            .init_stage()
+            .expect_err("The export name is already defined");
+        assert!(
+            err.to_string().contains("the symbol is already defined"),
+            "{export_name}: {err}"
+        );
+    }
+}
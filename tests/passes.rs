@@ -0,0 +1,134 @@
+//! The custom HUGR passes on hand-built HUGRs.
+
+use guppyc::compile::hugr::passes::HugrPass;
+use hugr::builder::{Dataflow, DataflowSubContainer, HugrBuilder, ModuleBuilder, SubContainer};
+use hugr::extension::prelude::bool_t;
+use hugr::ops::{OpType, Value};
+use hugr::std_extensions::arithmetic::int_ops::IntOpDef;
+use hugr::std_extensions::arithmetic::int_types::ConstInt;
+use hugr::type_row;
+use hugr::types::Signature;
+use hugr::{Hugr, HugrView};
+
+/// Count the nodes of a HUGR matching a predicate on their operation.
+fn count_ops(hugr: &Hugr, predicate: impl Fn(&OpType) -> bool) -> usize {
+    hugr.nodes()
+        .filter(|&n| predicate(hugr.get_optype(n)))
+        .count()
+}
+
+/// The names of the extension operations in a HUGR.
+fn extension_ops(hugr: &Hugr) -> Vec<String> {
+    hugr.nodes()
+        .filter_map(|n| match hugr.get_optype(n) {
+            OpType::ExtensionOp(op) => Some(op.def().name().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn inline_leaf_calls() -> anyhow::Result<()> {
+    // `main` calls `middle`, which calls `leaf`.
+    let mut module = ModuleBuilder::new();
+    let leaf = module.define_function("leaf", Signature::new_endo(vec![bool_t()]))?;
+    let [b] = leaf.input_wires_arr();
+    let leaf = leaf.finish_with_outputs([b])?;
+    let mut middle = module.define_function("middle", Signature::new_endo(vec![bool_t()]))?;
+    let [b] = middle.input_wires_arr();
+    let call = middle.call(leaf.handle(), &[], [b])?;
+    let middle = middle.finish_with_outputs(call.outputs())?;
+    let mut main = module.define_function("main", Signature::new_endo(vec![bool_t()]))?;
+    let [b] = main.input_wires_arr();
+    let call = main.call(middle.handle(), &[], [b])?;
+    main.finish_with_outputs(call.outputs())?;
+    let mut hugr = module.finish_hugr()?;
+
+    HugrPass::Inline.run(&mut hugr, None)?;
+    assert_eq!(count_ops(&hugr, |op| matches!(op, OpType::Call(_))), 0);
+    hugr.validate()?;
+    Ok(())
+}
+
+#[test]
+fn inline_nested_dfgs() -> anyhow::Result<()> {
+    let mut module = ModuleBuilder::new();
+    let mut main = module.define_function("main", Signature::new_endo(vec![bool_t()]))?;
+    let [b] = main.input_wires_arr();
+    let outer = main.dfg_builder(Signature::new_endo(vec![bool_t()]), [b])?;
+    let [b] = outer.input_wires_arr();
+    let outer = outer.finish_with_outputs([b])?;
+    main.finish_with_outputs(outer.outputs())?;
+    let mut hugr = module.finish_hugr()?;
+    assert_eq!(count_ops(&hugr, |op| matches!(op, OpType::DFG(_))), 1);
+
+    HugrPass::NormalizeDfgs.run(&mut hugr, None)?;
+    assert_eq!(count_ops(&hugr, |op| matches!(op, OpType::DFG(_))), 0);
+    hugr.validate()?;
+    Ok(())
+}
+
+#[test]
+fn merge_sequential_blocks() -> anyhow::Result<()> {
+    let mut module = ModuleBuilder::new();
+    let mut main = module.define_function("main", Signature::new_endo(vec![bool_t()]))?;
+    let [b] = main.input_wires_arr();
+    let mut cfg = main.cfg_builder([(bool_t(), b)], vec![bool_t()].into())?;
+    let mut entry = cfg.entry_builder([type_row![]], vec![bool_t()].into())?;
+    let [b] = entry.input_wires_arr();
+    let pred = entry.add_load_value(Value::unary_unit_sum());
+    let entry = entry.finish_with_outputs(pred, [b])?;
+    let mut block = cfg.simple_block_builder(Signature::new_endo(vec![bool_t()]), 1)?;
+    let [b] = block.input_wires_arr();
+    let pred = block.add_load_value(Value::unary_unit_sum());
+    let block = block.finish_with_outputs(pred, [b])?;
+    let exit = cfg.exit_block();
+    cfg.branch(&entry, 0, &block)?;
+    cfg.branch(&block, 0, &exit)?;
+    let cfg = cfg.finish_sub_container()?;
+    main.finish_with_outputs(cfg.outputs())?;
+    let mut hugr = module.finish_hugr()?;
+    let is_block = |op: &OpType| matches!(op, OpType::DataflowBlock(_));
+    assert_eq!(count_ops(&hugr, is_block), 2);
+
+    HugrPass::NormalizeCfgs.run(&mut hugr, None)?;
+    assert_eq!(count_ops(&hugr, is_block), 1);
+    hugr.validate()?;
+    Ok(())
+}
+
+#[test]
+fn dead_code_keeps_trapping_divisions() -> anyhow::Result<()> {
+    let mut module = ModuleBuilder::new();
+    let mut main = module.define_function("main", Signature::new(vec![], vec![]))?;
+    let x = main.add_load_value(ConstInt::new_u(6, 7)?);
+    let y = main.add_load_value(ConstInt::new_u(6, 0)?);
+    main.add_dataflow_op(IntOpDef::iadd.with_log_width(6), [x, y])?;
+    main.add_dataflow_op(IntOpDef::idiv_u.with_log_width(6), [x, y])?;
+    main.finish_with_outputs([])?;
+    let mut hugr = module.finish_hugr()?;
+
+    HugrPass::DeadCode.run(&mut hugr, None)?;
+    assert_eq!(extension_ops(&hugr), vec!["idiv_u".to_string()]);
+    hugr.validate()?;
+    Ok(())
+}
+
+#[test]
+fn dead_code_removes_unused_constants() -> anyhow::Result<()> {
+    let mut module = ModuleBuilder::new();
+    let mut main = module.define_function("main", Signature::new(vec![], vec![]))?;
+    let x = main.add_load_value(ConstInt::new_u(6, 7)?);
+    main.add_dataflow_op(IntOpDef::iadd.with_log_width(6), [x, x])?;
+    main.finish_with_outputs([])?;
+    let mut hugr = module.finish_hugr()?;
+
+    HugrPass::DeadCode.run(&mut hugr, None)?;
+    assert!(extension_ops(&hugr).is_empty());
+    assert_eq!(count_ops(&hugr, |op| matches!(op, OpType::Const(_))), 0);
+    assert_eq!(
+        count_ops(&hugr, |op| matches!(op, OpType::LoadConstant(_))),
+        0
+    );
+    Ok(())
+}
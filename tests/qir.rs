@@ -0,0 +1,135 @@
+//! QIR profile output for the `test_files` programs.
+
+mod common;
+
+use common::compile_llvm;
+use guppyc::compile::llvm::qir::{QirProfile, QirProfileError};
+use hugr::llvm::inkwell::context::Context;
+use hugr::llvm::inkwell::module::Module;
+
+/// Compile a program with a QIR profile, and check the module against it.
+fn check_profile(name: &str, profile: QirProfile) -> anyhow::Result<()> {
+    let llvm = compile_llvm(name, &["--qir-profile", &profile.to_string()])?;
+    let context = Context::create();
+    let module = Module::parse_bitcode_from_buffer(&llvm.module_bitcode, &context)
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+    let violations = profile.check_module(&module);
+    assert!(
+        violations.is_empty(),
+        "{name} violates the {profile} profile:\n{}\n{}",
+        violations
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        module.to_string()
+    );
+    Ok(())
+}
+
+/// Compile a program with a QIR profile, returning the entrypoint name and
+/// the LLVM text.
+fn profile_ir(name: &str, profile: QirProfile) -> anyhow::Result<(String, String)> {
+    let llvm = compile_llvm(name, &["--qir-profile", &profile.to_string()])?;
+    let entry = llvm.entrypoint.expect("`main` is the entrypoint");
+    let text = llvm.module_text.expect("The LLVM text was requested");
+    Ok((entry, text))
+}
+
+/// Assert that the LLVM text contains every snippet, and none of the forbidden ones.
+fn assert_ir(text: &str, expected: &[&str], forbidden: &[&str]) {
+    for snippet in expected {
+        assert!(text.contains(snippet), "Missing `{snippet}` in\n{text}");
+    }
+    for snippet in forbidden {
+        assert!(!text.contains(snippet), "Unexpected `{snippet}` in\n{text}");
+    }
+}
+
+#[test]
+fn base_straight_line() -> anyhow::Result<()> {
+    check_profile("planqc-1.py", QirProfile::Base)?;
+
+    let (entry, text) = profile_ir("planqc-1.py", QirProfile::Base)?;
+    assert_ir(
+        &text,
+        &[
+            &format!("define void @{entry}() "),
+            "call void @__quantum__rt__result_record_output(",
+            "\"required_num_qubits\"=\"1\"",
+            "\"required_num_results\"=\"1\"",
+            "\"qir_profiles\"=\"base_profile\"",
+            "call void @__quantum__qis__reset__body(",
+        ],
+        &[
+            "__quantum__rt__qubit_allocate",
+            "__quantum__rt__qubit_release",
+            "__quantum__qis__read_result__body",
+            "backwards_branching",
+        ],
+    );
+    Ok(())
+}
+
+#[test]
+fn base_rejects_loops() {
+    let err = compile_llvm("planqc-3.py", &["--qir-profile", "base"])
+        .expect_err("The base profile cannot express loops");
+    assert!(
+        matches!(
+            err.downcast_ref::<QirProfileError>(),
+            Some(QirProfileError::UnsupportedConstruct { .. })
+        ),
+        "{err}"
+    );
+}
+
+#[test]
+fn adaptive_teleport() -> anyhow::Result<()> {
+    check_profile("planqc-2.py", QirProfile::Adaptive)?;
+
+    // Two qubits are allocated by `main` and one by `teleport`, each measured once.
+    let (entry, text) = profile_ir("planqc-2.py", QirProfile::Adaptive)?;
+    assert_ir(
+        &text,
+        &[
+            &format!("define i64 @{entry}() "),
+            "ret i64 0",
+            "call void @__quantum__rt__result_record_output(",
+            "\"required_num_qubits\"=\"3\"",
+            "\"required_num_results\"=\"3\"",
+            "\"qir_profiles\"=\"adaptive_profile\"",
+        ],
+        &[
+            "__quantum__rt__qubit_allocate",
+            "__quantum__rt__qubit_release",
+            "backwards_branching",
+        ],
+    );
+    Ok(())
+}
+
+#[test]
+fn adaptive_repeat_until_success() -> anyhow::Result<()> {
+    check_profile("planqc-3.py", QirProfile::Adaptive)?;
+
+    // The loop reuses the addresses of the two qubits it allocates, resetting
+    // them when they are discarded.
+    let (entry, text) = profile_ir("planqc-3.py", QirProfile::Adaptive)?;
+    assert_ir(
+        &text,
+        &[
+            &format!("define i64 @{entry}() "),
+            "call void @__quantum__rt__result_record_output(",
+            "call void @__quantum__qis__reset__body(",
+            "\"required_num_qubits\"=\"3\"",
+            "\"required_num_results\"=\"3\"",
+            "!\"backwards_branching\"",
+        ],
+        &[
+            "__quantum__rt__qubit_allocate",
+            "__quantum__rt__qubit_release",
+        ],
+    );
+    Ok(())
+}
@@ -0,0 +1,148 @@
+//! The `qir-check` subcommand on hand-written LLVM modules.
+
+mod common;
+
+use clap::Parser;
+use common::temp_dir;
+use guppyc::cli::CliArgs;
+use guppyc::compile::llvm::parse_module;
+use guppyc::compile::llvm::qir::QirProfile;
+use guppyc::compile::llvm::qir::conformance::QirRule;
+use hugr::llvm::inkwell::context::Context;
+
+/// A module following the base profile, measuring a single qubit.
+const BASE_MODULE: &str = r#"%Qubit = type opaque
+%Result = type opaque
+
+@0 = internal constant [2 x i8] c"0\00"
+
+define void @main() #0 {
+entry:
+  call void @__quantum__qis__h__body(%Qubit* null)
+  call void @__quantum__qis__mz__body(%Qubit* null, %Result* null)
+  call void @__quantum__rt__result_record_output(%Result* null, i8* getelementptr inbounds ([2 x i8], [2 x i8]* @0, i32 0, i32 0))
+  ret void
+}
+
+declare void @__quantum__qis__h__body(%Qubit*)
+
+declare void @__quantum__qis__mz__body(%Qubit*, %Result*)
+
+declare void @__quantum__rt__result_record_output(%Result*, i8*)
+
+attributes #0 = { "EntryPoint" "output_labeling_schema"="labeled" "qir_profiles"="base_profile" "required_num_qubits"="1" "required_num_results"="1" }
+
+!llvm.module.flags = !{!0, !1, !2, !3}
+
+!0 = !{i32 1, !"qir_major_version", i32 1}
+!1 = !{i32 7, !"qir_minor_version", i32 0}
+!2 = !{i32 1, !"dynamic_qubit_management", i1 false}
+!3 = !{i32 1, !"dynamic_result_management", i1 false}
+"#;
+
+/// Run `guppyc qir-check` on an LLVM module written to a file, returning the
+/// rules it violates.
+///
+/// Asserts that the command fails exactly when there are violations.
+fn qir_check(test: &str, ir: &str, profile: QirProfile) -> anyhow::Result<Vec<QirRule>> {
+    let path = temp_dir(test).join("module.ll");
+    std::fs::write(&path, ir)?;
+
+    let profile_arg = profile.to_string();
+    let path_arg = path.to_str().unwrap();
+    let args = CliArgs::parse_from(["guppyc", "qir-check", "--profile", &profile_arg, path_arg]);
+    let result = args.command.expect("qir-check is a subcommand").run();
+
+    let context = Context::create();
+    let module = parse_module(&path, &context)?;
+    let rules = profile
+        .check_module(&module)
+        .into_iter()
+        .map(|v| v.rule)
+        .collect::<Vec<_>>();
+    assert_eq!(result.is_err(), !rules.is_empty(), "{result:?}");
+    Ok(rules)
+}
+
+/// Replace a snippet of the base module, which must be present.
+fn edit(replacements: &[(&str, &str)]) -> String {
+    let mut ir = BASE_MODULE.to_string();
+    for (from, to) in replacements {
+        assert!(ir.contains(from), "Missing `{from}` in the base module");
+        ir = ir.replace(from, to);
+    }
+    ir
+}
+
+#[test]
+fn valid_base_module() -> anyhow::Result<()> {
+    let rules = qir_check("valid_base_module", BASE_MODULE, QirProfile::Base)?;
+    assert!(rules.is_empty(), "{rules:?}");
+    Ok(())
+}
+
+#[test]
+fn forbidden_instruction() -> anyhow::Result<()> {
+    let ir = edit(&[("entry:\n", "entry:\n  %x = alloca i64\n")]);
+    let rules = qir_check("forbidden_instruction", &ir, QirProfile::Base)?;
+    assert_eq!(rules, [QirRule::Instruction]);
+    Ok(())
+}
+
+#[test]
+fn wrong_intrinsic_signature() -> anyhow::Result<()> {
+    let ir = edit(&[
+        (
+            "call void @__quantum__qis__h__body(%Qubit* null)",
+            "call void @__quantum__qis__h__body(%Qubit* null, i64 0)",
+        ),
+        (
+            "declare void @__quantum__qis__h__body(%Qubit*)",
+            "declare void @__quantum__qis__h__body(%Qubit*, i64)",
+        ),
+    ]);
+    let rules = qir_check("wrong_intrinsic_signature", &ir, QirProfile::Base)?;
+    assert_eq!(rules, [QirRule::Signature]);
+    Ok(())
+}
+
+#[test]
+fn missing_entrypoint() -> anyhow::Result<()> {
+    let ir = edit(&[("\"EntryPoint\" ", "")]);
+    let rules = qir_check("missing_entrypoint", &ir, QirProfile::Base)?;
+    // Without an entrypoint, the body of `main` is not allowed either.
+    assert_eq!(rules, [QirRule::EntryPoint, QirRule::Definition]);
+    Ok(())
+}
+
+#[test]
+fn backward_branch() -> anyhow::Result<()> {
+    let ir = edit(&[
+        ("entry:\n", "entry:\n  br label %body\n\nbody:\n"),
+        ("  ret void\n", "  br label %body\n"),
+    ]);
+    let rules = qir_check("backward_branch", &ir, QirProfile::Base)?;
+    assert_eq!(rules, [QirRule::Branching]);
+    Ok(())
+}
+
+#[test]
+fn wrong_entry_signature() -> anyhow::Result<()> {
+    let ir = edit(&[("define void @main()", "define void @main(i64 %shots)")]);
+    let rules = qir_check("wrong_entry_signature", &ir, QirProfile::Base)?;
+    assert_eq!(rules, [QirRule::EntrySignature]);
+    Ok(())
+}
+
+#[test]
+fn unknown_module_format() -> anyhow::Result<()> {
+    let path = temp_dir("unknown_module_format").join("module.txt");
+    std::fs::write(&path, BASE_MODULE)?;
+    let context = Context::create();
+    let err = parse_module(&path, &context).expect_err("`.txt` is not an LLVM format");
+    assert!(
+        err.to_string().contains("Unknown LLVM module format"),
+        "{err}"
+    );
+    Ok(())
+}
@@ -0,0 +1,113 @@
+//! Graphviz and HTML views of the HUGR.
+
+mod common;
+
+use common::load_hugr;
+use guppyc::compile::hugr::functions;
+use guppyc::compile::hugr::render::{self, RenderOptions};
+use hugr::{HugrView, Node, NodeIndex};
+
+/// The node of a function defined in a HUGR.
+fn function_node(hugr: &impl HugrView, name: &str) -> Node {
+    functions::functions(hugr)
+        .into_iter()
+        .find(|f| f.qualified_name == name)
+        .unwrap_or_else(|| panic!("No function {name}"))
+        .node
+}
+
+#[test]
+fn dot_clusters_every_container() -> anyhow::Result<()> {
+    let stage = load_hugr("even_odd.py")?;
+    let hugr = stage.hugr();
+    let options = RenderOptions {
+        root: hugr.root(),
+        max_depth: None,
+    };
+    let dot = render::dot_string(hugr, &options);
+
+    assert!(dot.starts_with("digraph hugr {"), "{dot}");
+    let containers = hugr
+        .nodes()
+        .filter(|&n| hugr.children(n).next().is_some())
+        .count();
+    assert_eq!(dot.matches("subgraph cluster_").count(), containers);
+    assert!(!dot.contains("(collapsed)"));
+    for node in hugr.nodes() {
+        assert!(
+            dot.contains(&format!("n{} [", node.index())),
+            "Node {node} is not drawn"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn dot_edges_into_collapsed_functions() -> anyhow::Result<()> {
+    let stage = load_hugr("even_odd.py")?;
+    let hugr = stage.hugr();
+    let options = RenderOptions {
+        root: hugr.root(),
+        max_depth: Some(1),
+    };
+    let dot = render::dot_string(hugr, &options);
+
+    // The functions are collapsed, and the calls between them are drawn
+    // between the collapsed functions.
+    assert_eq!(dot.matches("subgraph cluster_").count(), 1);
+    let is_even = function_node(hugr, "is_even");
+    let is_odd = function_node(hugr, "is_odd");
+    for (caller, callee) in [(is_even, is_odd), (is_odd, is_even)] {
+        let edge = format!("n{} -> n{} [", callee.index(), caller.index());
+        assert!(dot.contains(&edge), "Missing {edge} in\n{dot}");
+    }
+    Ok(())
+}
+
+#[test]
+fn dot_single_function() -> anyhow::Result<()> {
+    let stage = load_hugr("even_odd.py")?;
+    let hugr = stage.hugr();
+    let main = function_node(hugr, "main");
+    let options = RenderOptions {
+        root: main,
+        max_depth: None,
+    };
+    let dot = render::dot_string(hugr, &options);
+
+    assert!(dot.contains("FuncDefn: main"));
+    assert!(!dot.contains("FuncDefn: is_even"));
+    // Only the nodes inside `main` are connected.
+    let drawn = hugr.descendants(main).collect::<Vec<_>>();
+    for line in dot.lines().filter(|line| line.contains(" -> ")) {
+        let source = line.trim().split(' ').next().unwrap();
+        assert!(
+            drawn.iter().any(|n| format!("n{}", n.index()) == source),
+            "{line}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn html_collapses_deep_regions() -> anyhow::Result<()> {
+    let stage = load_hugr("even_odd.py")?;
+    let hugr = stage.hugr();
+    let options = RenderOptions {
+        root: hugr.root(),
+        max_depth: Some(1),
+    };
+    let html = render::html_string(hugr, &options);
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert_eq!(html.matches("<details open>").count(), 1);
+    for name in ["main", "is_even", "is_odd"] {
+        let node = function_node(hugr, name);
+        let summary = format!(
+            "<details><summary>FuncDefn: {name} <span class=\"node-id\">n{}</span>",
+            node.index()
+        );
+        assert!(html.contains(&summary), "Missing {summary}");
+    }
+    Ok(())
+}
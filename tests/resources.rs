@@ -0,0 +1,139 @@
+//! Quantum resource reports.
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::load_hugr;
+use guppyc::compile::hugr::passes::HugrPass;
+use guppyc::inspect::resources::{ResourceReport, Resources};
+use hugr::NodeIndex;
+use hugr::builder::{Dataflow, DataflowSubContainer, HugrBuilder, ModuleBuilder, SubContainer};
+use hugr::extension::prelude::bool_t;
+use hugr::type_row;
+use hugr::types::Signature;
+use tket2::Tk2Op;
+
+/// The resource report of a program in `test_files`, for its `main` entrypoint.
+fn report(name: &str) -> anyhow::Result<ResourceReport> {
+    let mut stage = load_hugr(name)?;
+    HugrPass::Monomorphize.run(stage.hugr_mut(), None)?;
+    let entrypoint = stage.entrypoint(None)?;
+    Ok(ResourceReport::new(stage.hugr(), entrypoint))
+}
+
+/// The resources used by a function in a report.
+fn function<'r>(report: &'r ResourceReport, name: &str) -> &'r Resources {
+    &report
+        .functions
+        .iter()
+        .find(|f| f.name == name)
+        .unwrap_or_else(|| panic!("No function {name} in {report:?}"))
+        .resources
+}
+
+fn gates(counts: &[(&str, usize)]) -> BTreeMap<String, usize> {
+    counts
+        .iter()
+        .map(|&(gate, count)| (gate.to_string(), count))
+        .collect()
+}
+
+#[test]
+fn teleport_resources() -> anyhow::Result<()> {
+    let report = report("planqc-2.py")?;
+
+    let teleport = function(&report, "teleport");
+    assert_eq!(teleport.static_qubits, 3);
+    assert_eq!(teleport.allocations, 1);
+    assert_eq!(teleport.measurements, 2);
+    assert_eq!(teleport.max_live_qubits, 3);
+    assert!(teleport.classical_control);
+    // The measurement returned by `main` does not decide the control flow.
+    assert!(!function(&report, "main").classical_control);
+
+    let entry = report.entrypoint.as_ref().expect("main is the entrypoint");
+    let total = &entry.resources;
+    assert_eq!(entry.name, "main");
+    assert_eq!(total.static_qubits, 3);
+    assert_eq!(total.allocations, 3);
+    assert_eq!(total.measurements, 3);
+    assert_eq!(total.max_live_qubits, 3);
+    assert!(total.classical_control);
+    assert_eq!(
+        total.gates,
+        gates(&[("CX", 2), ("H", 2), ("X", 1), ("Z", 1)])
+    );
+    Ok(())
+}
+
+#[test]
+fn repeat_until_success_resources() -> anyhow::Result<()> {
+    let report = report("planqc-3.py")?;
+
+    // The two allocations in the loop body are counted once.
+    let rus = function(&report, "rus");
+    assert_eq!(rus.static_qubits, 3);
+    assert_eq!(rus.allocations, 2);
+    assert_eq!(rus.measurements, 2);
+    assert_eq!(rus.max_live_qubits, 3);
+    assert!(rus.classical_control);
+
+    let total = &report
+        .entrypoint
+        .as_ref()
+        .expect("main is the entrypoint")
+        .resources;
+    assert_eq!(total.static_qubits, 3);
+    assert_eq!(total.allocations, 3);
+    assert_eq!(total.measurements, 3);
+    assert_eq!(total.max_live_qubits, 3);
+    assert!(total.classical_control);
+    assert_eq!(
+        total.gates,
+        gates(&[
+            ("CX", 2),
+            ("H", 2),
+            ("T", 3),
+            ("Tdg", 1),
+            ("X", 1),
+            ("Z", 1)
+        ])
+    );
+    Ok(())
+}
+
+#[test]
+fn control_through_regions_and_calls() -> anyhow::Result<()> {
+    // `main` passes a measurement through a nested DFG to a function branching on it.
+    let mut module = ModuleBuilder::new();
+    let mut branch = module.define_function("branch", Signature::new(vec![bool_t()], vec![]))?;
+    let [b] = branch.input_wires_arr();
+    let mut conditional =
+        branch.conditional_builder(([type_row![], type_row![]], b), [], type_row![])?;
+    for case in 0..2 {
+        conditional.case_builder(case)?.finish_with_outputs([])?;
+    }
+    conditional.finish_sub_container()?;
+    let branch = branch.finish_with_outputs([])?;
+
+    let mut main = module.define_function("main", Signature::new(vec![], vec![]))?;
+    let q = main.add_dataflow_op(Tk2Op::QAlloc, [])?.out_wire(0);
+    let m = main.add_dataflow_op(Tk2Op::MeasureFree, [q])?.out_wire(0);
+    let mut dfg = main.dfg_builder(Signature::new(vec![bool_t()], vec![]), [m])?;
+    let [m] = dfg.input_wires_arr();
+    dfg.call(branch.handle(), &[], [m])?;
+    dfg.finish_with_outputs([])?;
+    let main = main.finish_with_outputs([])?;
+    let hugr = module.finish_hugr()?;
+
+    let report = ResourceReport::new(&hugr, Some(main.node()));
+    let main_resources = function(&report, "main");
+    assert_eq!(main_resources.measurements, 1);
+    assert!(main_resources.classical_control);
+    assert_eq!(
+        report.entrypoint.map(|entry| entry.node),
+        Some(main.node().index())
+    );
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Code generation for explicitly selected targets.
+
+mod common;
+
+use std::fs;
+
+use clap::Parser;
+use common::{compile_llvm, temp_dir};
+use guppyc::cli::CliArgs;
+use guppyc::compile::{CompilationStage, GenericStage};
+
+/// Target triples, along with a fragment of their data layout and their ELF
+/// machine number.
+const TARGETS: &[(&str, &str, u16)] = &[
+    ("aarch64-unknown-linux-gnu", "-i128:128-n32:64-", 183),
+    ("x86_64-unknown-linux-gnu", "-f80:128-n8:16:32:64-", 62),
+];
+
+#[test]
+fn cross_compile_objects() -> anyhow::Result<()> {
+    for &(triple, layout, machine) in TARGETS {
+        let llvm = compile_llvm("planqc-1.py", &["--target", triple, "--object", "out.o"])?;
+
+        let text = llvm.module_text.expect("The LLVM text was requested");
+        assert!(
+            text.contains(&format!("target triple = \"{triple}\"")),
+            "{text}"
+        );
+        let datalayout = text
+            .lines()
+            .find(|line| line.starts_with("target datalayout"))
+            .unwrap_or_else(|| panic!("No data layout for {triple}:\n{text}"));
+        assert!(datalayout.contains(layout), "{triple}: {datalayout}");
+
+        let object = llvm.object.expect("The object was requested");
+        let object = object.as_slice();
+        assert_eq!(&object[..4], b"\x7fELF", "{triple}");
+        let e_machine = u16::from_le_bytes([object[18], object[19]]);
+        assert_eq!(e_machine, machine, "{triple}");
+    }
+    Ok(())
+}
+
+#[test]
+fn cross_compile_assembly() -> anyhow::Result<()> {
+    // The return instruction is spelled differently on each target.
+    for (triple, ret, other_ret) in [
+        ("aarch64-unknown-linux-gnu", "\tret\n", "retq"),
+        ("x86_64-unknown-linux-gnu", "\tretq\n", "\tret\n"),
+    ] {
+        let llvm = compile_llvm("planqc-1.py", &["--target", triple, "--asm", "out.s"])?;
+        let assembly = llvm.assembly.expect("The assembly was requested");
+        let assembly = std::str::from_utf8(assembly.as_slice())?;
+        assert!(!assembly.trim().is_empty(), "{triple}");
+        assert!(assembly.contains(ret), "{triple}:\n{assembly}");
+        assert!(!assembly.contains(other_ret), "{triple}:\n{assembly}");
+    }
+    Ok(())
+}
+
+#[test]
+fn emit_invalid_skips_assembly() -> anyhow::Result<()> {
+    let dir = temp_dir("emit_invalid_skips_assembly");
+    let (input, llvm_out, asm_out) = (dir.join("in.ll"), dir.join("out.ll"), dir.join("out.s"));
+    // `%x` does not dominate its use.
+    fs::write(
+        &input,
+        "define i64 @main(i1 %c) {
+entry:
+  br i1 %c, label %a, label %b
+a:
+  %x = add i64 1, 2
+  br label %b
+b:
+  ret i64 %x
+}
+",
+    )?;
+
+    let args = CliArgs::parse_from([
+        "guppyc",
+        "--llvm-input",
+        input.to_str().unwrap(),
+        "--target",
+        TARGETS[0].0,
+        "--emit-invalid",
+        "--llvm",
+        llvm_out.to_str().unwrap(),
+        "--asm",
+        asm_out.to_str().unwrap(),
+    ]);
+    let GenericStage::LLVM(llvm) = args.init_stage()? else {
+        panic!("--llvm-input starts at the LLVM stage");
+    };
+    assert!(llvm.assembly.is_none());
+    llvm.store(&args)?;
+    assert!(fs::read_to_string(&llvm_out)?.contains("ret i64 %x"));
+    assert!(!asm_out.exists());
+    Ok(())
+}